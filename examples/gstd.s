@@ -6,6 +6,47 @@ g_args     = $6001 ; start of gpu arg vector
 g_run      = $6000 ; run command if written to
 g_ptr      = $fe   ; ptr storage on zeropage
 g_key_addr = $6100 ; address to read if key is down
+g_mode     = $6101 ; gpu mode, bit 0 -> coordinates are words
+g_version  = $6102 ; gpu protocol version
+g_caps     = $6103 ; capability bits, word
+g_status   = $6105 ; status of the last command, 0 -> ok
+g_err_cmd  = $6106 ; last unknown command
+g_frames   = $6108 ; frame counter, 32 bit
+g_vblank   = $610c ; bit 7 -> vblank happened, cleared on read
+g_vsync_ctl = $610d ; 1 -> vblank IRQ, 2 -> free running
+g_mouse_x  = $6110 ; mouse x, word
+g_mouse_y  = $6112 ; mouse y, word
+g_mouse_btn = $6114 ; mouse buttons, 1 -> left, 2 -> right, 4 -> middle
+g_mouse_wheel = $6115 ; wheel movement this frame, signed
+g_key_count = $6120 ; amount of key events
+g_key_event = $6121 ; pops a key event, 1 -> pressed, 2 -> released
+g_key_code  = $6122 ; key code of the popped event, word
+g_char_count = $6124 ; amount of typed characters
+g_char      = $6125 ; pops a typed character
+g_key_irq   = $6126 ; 1 -> IRQ on key events, 2 -> IRQ on characters
+; controller, bits: up, down, left, right, A, B, select, start
+; then X, Y, L, R in the high byte
+g_pad       = $6130
+g_pad_status = $6132 ; 1 -> host gamepad connected
+g_pad_keymap = $6140 ; key codes used without a gamepad, words
+; sprite attribute table, 32 entries of
+; {x, y, pattern, r, g, b, flags, high}
+; flags: 1 -> enable, 2 -> flip x, 4 -> flip y, 8 -> double size
+; high: wide mode only, bits 8-11 of x in the low nibble
+; and bits 8-11 of y in the high nibble
+g_sprites  = $6200
+g_sprite_status = $6300 ; bit 7 set if 2 sprites collided, cleared on read
+g_sprite_hit_a  = $6301 ; first sprite of the collision
+g_sprite_hit_b  = $6302 ; second sprite of the collision
+g_palette  = $6400 ; 256 palette entries of {r, g, b}
+g_patterns = $7800 ; sprite patterns, 8x8 pixels, 1 byte per line
+; dma controller, copies while the cpu waits
+dma_run    = $4070 ; 1 -> copy, 2 -> fill
+dma_src    = $4071 ; word
+dma_dst    = $4073 ; word
+dma_len    = $4075 ; word
+dma_value  = $4077 ; fill byte
+dma_mode   = $4078 ; 1 -> source stays, 2 -> destination stays
 
   ; A -> Keycode to check
   ; returns 1 in A if key is down
@@ -106,4 +147,174 @@ _draw_line_arg_loop:
   pla
   tax 
   pla
-  rts
\ No newline at end of file
+  rts
+
+  ; raylib::ClearBackground with a palette colour
+  ; A -> palette index
+clear_background_i:
+  sta g_args
+  lda #$cc ; ClearBackground, indexed
+  sta g_run
+  rts
+
+  ; raylib::DrawRectangle with a palette colour
+  ; g_ptr -> *{x, y, w, h, i} rect "object"
+draw_rectangle_i:
+  pha ; save A, X and Y
+  txa 
+  pha
+  tya
+  pha
+  ldy #0
+_draw_rectangle_i_arg_loop:
+  ; load args into gpu arg vector
+  lda (g_ptr), Y
+  sta g_args, Y
+  iny
+  cpy #5 ; 5 args
+  bne _draw_rectangle_i_arg_loop
+  lda #$d6 ; DrawRectangle, indexed
+  sta g_run
+  pla ; restore A, X and Y
+  tay
+  pla
+  tax 
+  pla 
+  rts
+
+  ; raylib::DrawLine with a palette colour
+  ; g_ptr -> *{x1, y1, x2, y2, i}
+draw_line_i:
+  pha ; save A, X and Y
+  txa 
+  pha
+  tya
+  pha
+  ldy #0
+_draw_line_i_arg_loop:
+  lda (g_ptr), Y
+  sta g_args, Y
+  iny
+  cpy #5 ; 5 args
+  bne _draw_line_i_arg_loop
+  lda #$d2 ; DrawLine, indexed
+  sta g_run
+  pla ; restore A, X and Y
+  tay
+  pla
+  tax 
+  pla
+  rts
+
+  ; copies Y arguments from g_ptr
+  ; into the gpu arg vector and runs 
+  ; the command in A
+  ; jumped to by the commands below after
+  ; they pushed A and Y, which get restored here
+  ; g_ptr -> *args
+  ; A -> command
+  ; Y -> amount of args
+run_command:
+  pha ; save command
+_run_command_arg_loop:
+  dey
+  lda (g_ptr), Y
+  sta g_args, Y
+  cpy #0
+  bne _run_command_arg_loop
+  pla ; get command back
+  sta g_run
+  pla ; restore A and Y of the caller
+  tay
+  pla
+  rts
+
+  ; raylib::DrawPixel
+  ; g_ptr -> *{x, y, r, g, b}
+draw_pixel:
+  pha ; save A and Y, run_command restores them
+  tya
+  pha
+  ldy #5
+  lda #$d8
+  jmp run_command
+
+  ; raylib::DrawRectangleLines
+  ; g_ptr -> *{x, y, w, h, r, g, b}
+draw_rectangle_lines:
+  pha ; save A and Y, run_command restores them
+  tya
+  pha
+  ldy #7
+  lda #$d7
+  jmp run_command
+
+  ; raylib::DrawCircle
+  ; g_ptr -> *{x, y, radius, r, g, b}
+draw_circle:
+  pha ; save A and Y, run_command restores them
+  tya
+  pha
+  ldy #6
+  lda #$dc
+  jmp run_command
+
+  ; raylib::DrawCircleLines
+  ; g_ptr -> *{x, y, radius, r, g, b}
+draw_circle_lines:
+  pha ; save A and Y, run_command restores them
+  tya
+  pha
+  ldy #6
+  lda #$dd
+  jmp run_command
+
+  ; raylib::DrawTriangle
+  ; g_ptr -> *{x1, y1, x2, y2, x3, y3, r, g, b}
+draw_triangle:
+  pha ; save A and Y, run_command restores them
+  tya
+  pha
+  ldy #9
+  lda #$d3
+  jmp run_command
+
+  ; raylib::DrawText
+  ; g_ptr -> *{*str, x, y, font size, r, g, b}
+draw_text:
+  pha ; save A and Y, run_command restores them
+  tya
+  pha
+  ldy #8
+  lda #$da
+  jmp run_command
+
+  ; define a texture from a bitmap
+  ; g_ptr -> *{slot, *bitmap, w, h, bpp, palette base, flags}
+define_texture:
+  pha ; save A and Y, run_command restores them
+  tya
+  pha
+  ldy #8
+  lda #$e0
+  jmp run_command
+
+  ; draw a whole texture
+  ; g_ptr -> *{slot, x, y, scale, flags}
+draw_texture:
+  pha ; save A and Y, run_command restores them
+  tya
+  pha
+  ldy #5
+  lda #$e1
+  jmp run_command
+
+  ; draw one tile of a texture
+  ; g_ptr -> *{slot, tile, tile w, tile h, x, y, scale, flags}
+draw_tile:
+  pha ; save A and Y, run_command restores them
+  tya
+  pha
+  ldy #8
+  lda #$e2
+  jmp run_command
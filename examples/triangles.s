@@ -0,0 +1,43 @@
+; draws one triangle with its points in
+; clockwise and one in counter-clockwise order,
+; both should show up
+
+  .segment "CODE"
+reset:
+  jsr begin_drawing
+  lda #<background_colour
+  sta z:g_ptr
+  lda #>background_colour
+  sta z:g_ptr + 1
+  jsr clear_background
+  ; clockwise on screen
+  lda #<clockwise
+  sta z:g_ptr
+  lda #>clockwise
+  sta z:g_ptr + 1
+  jsr draw_triangle
+  ; counter-clockwise on screen
+  lda #<counter_clockwise
+  sta z:g_ptr
+  lda #>counter_clockwise
+  sta z:g_ptr + 1
+  jsr draw_triangle
+  jsr end_drawing
+  jmp reset
+
+  .include "gstd.s"
+
+background_colour: .byte $10, $10, $10
+; {x1, y1, x2, y2, x3, y3, r, g, b}
+clockwise: .byte 10, 10, 50, 10, 30, 50, $ff, $91, $ff
+counter_clockwise: .byte 70, 10, 90, 50, 110, 10, $91, $ff, $ff
+window_title: .asciiz "Triangles"
+  .segment "GPU_DATA"
+  .byte $01 ; enable GPU mode
+  .word 120 ; window width
+  .word 60 ; window height
+  .word window_title ; 2 bytes
+  .byte 4   ; window scale
+  .byte 30  ; framerate
+  .segment "RV" ; reset vector
+  .word reset
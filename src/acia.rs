@@ -0,0 +1,163 @@
+use crate::Bus;
+
+/// Where the ACIA sits, same place as on Ben Eaters breadboard computer
+pub static ACIA_LOC: u16 = 0x5000;
+/// Received byte on read, byte to send on write
+static ACIA_DATA: u16 = 0;
+/// Status on read, writing anything does a programmed reset
+static ACIA_STATUS: u16 = 1;
+static ACIA_COMMAND: u16 = 2;
+/// Baud rate and word length, stored but nothing uses it
+static ACIA_CONTROL: u16 = 3;
+/// Command register after a reset, receiver IRQ disabled
+static COMMAND_RESET: u8 = 0b00000010;
+
+#[repr(u8)]
+/// Bits of the status register
+enum AciaStatus {
+    // the ACIA wants an IRQ, cleared by reading the status or data
+    Irq         = 0b10000000,
+    // the transmitter is always ready since the host is way faster than any baud rate
+    TxEmpty     = 0b00010000,
+    // a byte was received and not read yet
+    RxFull      = 0b00001000,
+    // a byte came in while the last one wasnt read
+    Overrun     = 0b00000100,
+}
+
+#[repr(u8)]
+/// Bits of the command register
+enum AciaCommand {
+    // data terminal ready, the receiver only works with this set
+    Dtr         = 0b00000001,
+    // receiver IRQ disabled
+    RxIrqOff    = 0b00000010,
+    // bits 2-3, 01 means transmitter IRQ on
+    TxControl   = 0b00001100,
+    // send received bytes straight back
+    Echo        = 0b00010000,
+}
+
+#[derive(Clone)]
+/// 6551 ACIA serial port, connected to the host terminal
+pub struct Acia {
+    /// Only there if turned on with --acia
+    pub enabled: bool,
+    rx: u8,
+    status: u8,
+    command: u8,
+    control: u8,
+    /// The last byte sent was a CR, for turning CR LF into one newline
+    after_cr: bool,
+}
+
+impl Acia {
+    pub fn init() -> Acia {
+        Acia {
+            enabled: false,
+            rx: 0,
+            status: AciaStatus::TxEmpty as u8,
+            command: COMMAND_RESET,
+            control: 0,
+            after_cr: false,
+        }
+    }
+
+    fn has(&self, bit: AciaCommand) -> bool {
+        self.command & bit as u8 != 0
+    }
+
+    /// Transmitter IRQ is on when bits 2-3 of the command are 01
+    fn tx_irq(&self) -> bool {
+        self.command & AciaCommand::TxControl as u8 == 0b00000100
+    }
+
+    /// Whether the ACIA pulls the IRQ line
+    pub fn irq(&self) -> bool {
+        self.enabled && (self.status & AciaStatus::Irq as u8 != 0 || self.tx_irq())
+    }
+}
+
+impl Bus {
+    /// Read one of the 4 ACIA registers
+    pub fn read_acia(&mut self, addr: u16) -> u8 {
+        let acia = &mut self.acia;
+        match addr - ACIA_LOC {
+            r if r == ACIA_DATA => {
+                acia.status &= !(AciaStatus::RxFull as u8 | AciaStatus::Overrun as u8 | AciaStatus::Irq as u8);
+                acia.rx
+            }
+            r if r == ACIA_STATUS => {
+                // reading acknowledges the IRQ
+                let mut status = acia.status;
+                if acia.tx_irq() {
+                    status |= AciaStatus::Irq as u8;
+                }
+                acia.status &= !(AciaStatus::Irq as u8);
+                if status & AciaStatus::RxFull as u8 == 0 {
+                    self.terminal.wait_for_key();
+                }
+                status
+            }
+            r if r == ACIA_COMMAND => acia.command,
+            r if r == ACIA_CONTROL => acia.control,
+            _ => 0
+        }
+    }
+
+    /// Write one of the 4 ACIA registers
+    pub fn write_acia(&mut self, addr: u16, byte: u8) {
+        match addr - ACIA_LOC {
+            r if r == ACIA_DATA => self.acia_send(byte),
+            r if r == ACIA_STATUS => {
+                // programmed reset, keeps the parity bits
+                self.acia.command = self.acia.command & 0b11100000 | COMMAND_RESET;
+                self.acia.status &= !(AciaStatus::Overrun as u8);
+            }
+            r if r == ACIA_COMMAND => self.acia.command = byte,
+            r if r == ACIA_CONTROL => self.acia.control = byte,
+            _ => {}
+        }
+    }
+
+    /**
+    Send a byte to the terminal
+
+    Monitor ROMs end lines with just a CR, others with CR LF, so unless
+    the output is raw a CR becomes a newline and a LF right after it gets dropped
+    */
+    fn acia_send(&mut self, byte: u8) {
+        let after_cr = self.acia.after_cr;
+        self.acia.after_cr = byte == 13;
+        if self.terminal.is_raw() {
+            self.terminal.write(byte);
+        } else if byte == 13 {
+            self.terminal.write(10);
+        } else if !(byte == 10 && after_cr) {
+            self.terminal.write(byte);
+        }
+    }
+
+    /// Move the next key from the terminal into the receive register once its free
+    pub fn tick_acia(&mut self) {
+        let acia = &self.acia;
+        if !acia.enabled || !acia.has(AciaCommand::Dtr) || acia.status & AciaStatus::RxFull as u8 != 0 {
+            return
+        }
+        let Some(mut byte) = self.terminal.pop_key() else {
+            return
+        };
+        // terminals send a CR for enter
+        if byte == 10 {
+            byte = 13;
+        }
+        self.acia.rx = byte;
+        self.acia.status |= AciaStatus::RxFull as u8;
+        if !self.acia.has(AciaCommand::RxIrqOff) {
+            self.acia.status |= AciaStatus::Irq as u8;
+        }
+        if self.acia.has(AciaCommand::Echo) {
+            self.acia_send(byte);
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
+
+use crate::Bus;
+
+/// Start of the disk registers, writing here runs a DiskCommand
+pub static DISK_LOC: usize = 0x4060;
+/// First sector to transfer, 32 bit little endian, moves past the sectors afterwards
+static DISK_LBA: usize = 0x1;
+/// How many sectors to transfer
+static DISK_COUNT: usize = 0x5;
+/// Where the sectors go to or come from (word)
+static DISK_BUFFER: usize = 0x6;
+/// See DiskStatus
+static DISK_STATUS: usize = 0x8;
+/// Size of the image in sectors, 32 bit little endian
+static DISK_SECTORS: usize = 0x9;
+/// Bytes per sector
+pub static SECTOR_SIZE: usize = 512;
+
+#[derive(Debug, FromPrimitive, Clone, Copy)]
+/// Values of the command register
+enum DiskCommand {
+    /// copy sectors from the image into memory
+    Read    = 0x01,
+    /// copy sectors from memory into the image
+    Write   = 0x02,
+}
+
+#[repr(u8)]
+/// Values of the status register
+enum DiskStatus {
+    Ok              = 0x00,
+    // the command byte isnt a DiskCommand
+    UnknownCommand  = 0x01,
+    // the sectors go past the end of the image
+    OutOfRange      = 0x02,
+    // the host couldnt read or write the image
+    IoError         = 0x03,
+}
+
+#[derive(Clone)]
+/// Block device backed by a disk image
+pub struct Disk {
+    /// Only there if an image was given with --disk
+    image: Option<PathBuf>,
+    regs: [u8; 16],
+    sectors: u32,
+}
+
+impl Disk {
+    pub fn init() -> Disk {
+        Disk {
+            image: None,
+            regs: [0; 16],
+            sectors: 0,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.image.is_some()
+    }
+
+    /// Use this image, its size has to be a multiple of the sector size
+    pub fn attach(&mut self, path: &str) -> io::Result<()> {
+        let len = fs::metadata(path)?.len();
+        if len % SECTOR_SIZE as u64 != 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,
+                format!("disk image size has to be a multiple of {} bytes", SECTOR_SIZE)))
+        }
+        self.sectors = (len / SECTOR_SIZE as u64).min(u32::MAX as u64) as u32;
+        let base = DISK_SECTORS;
+        self.regs[base..base + 4].copy_from_slice(&self.sectors.to_le_bytes());
+        self.image = Some(PathBuf::from(path));
+        Ok(())
+    }
+
+    fn lba(&self) -> u32 {
+        u32::from_le_bytes(self.regs[DISK_LBA..DISK_LBA + 4].try_into().unwrap())
+    }
+
+    fn buffer(&self) -> u16 {
+        self.regs[DISK_BUFFER] as u16 | (self.regs[DISK_BUFFER + 1] as u16) << 8
+    }
+}
+
+impl Bus {
+    pub fn read_disk(&self, addr: u16) -> u8 {
+        self.disk.regs[addr as usize - DISK_LOC]
+    }
+
+    /// Write the disk registers, writing the command register runs it
+    pub fn write_disk(&mut self, addr: u16, byte: u8) {
+        let reg = addr as usize - DISK_LOC;
+        // size is read only
+        if (DISK_SECTORS..DISK_SECTORS + 4).contains(&reg) {
+            return
+        }
+        self.disk.regs[reg] = byte;
+        if reg == 0 {
+            let status = match self.run_disk_command(byte) {
+                Ok(()) => DiskStatus::Ok,
+                Err(status) => status
+            };
+            self.disk.regs[DISK_STATUS] = status as u8;
+        }
+    }
+
+    fn run_disk_command(&mut self, byte: u8) -> Result<(), DiskStatus> {
+        let Some(cmd) = DiskCommand::from_u8(byte) else {
+            return Err(DiskStatus::UnknownCommand)
+        };
+        let Some(path) = self.disk.image.clone() else {
+            return Err(DiskStatus::IoError)
+        };
+        let lba = self.disk.lba();
+        let count = self.disk.regs[DISK_COUNT] as u32;
+        if lba as u64 + count as u64 > self.disk.sectors as u64 {
+            return Err(DiskStatus::OutOfRange)
+        }
+        let offset = lba as u64 * SECTOR_SIZE as u64;
+        let len = count as usize * SECTOR_SIZE;
+        let buffer = self.disk.buffer();
+        match cmd {
+            DiskCommand::Read => {
+                let mut data = vec![0; len];
+                fs::File::open(&path)
+                    .and_then(|mut f| {
+                        f.seek(SeekFrom::Start(offset))?;
+                        f.read_exact(&mut data)
+                    })
+                    .map_err(|_| DiskStatus::IoError)?;
+                // straight into memory
+                for (i, byte) in data.into_iter().enumerate() {
+                    self.write(buffer.wrapping_add(i as u16), byte);
+                }
+            }
+            DiskCommand::Write => {
+                let data: Vec<u8> = (0..len).map(|i| self.read(buffer.wrapping_add(i as u16))).collect();
+                fs::OpenOptions::new().write(true).open(&path)
+                    .and_then(|mut f| {
+                        f.seek(SeekFrom::Start(offset))?;
+                        f.write_all(&data)
+                    })
+                    .map_err(|_| DiskStatus::IoError)?;
+            }
+        }
+        // ready for the next sectors
+        self.disk.regs[DISK_LBA..DISK_LBA + 4].copy_from_slice(&(lba + count).to_le_bytes());
+        Ok(())
+    }
+}
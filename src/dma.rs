@@ -0,0 +1,121 @@
+use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
+
+use crate::Bus;
+
+/// Start of the DMA registers, writing here runs a DmaCommand
+pub static DMA_LOC: usize = 0x4070;
+/// Where a copy reads from (word)
+static DMA_SOURCE: usize = 0x1;
+/// Where the bytes get written to (word)
+static DMA_DEST: usize = 0x3;
+/// How many bytes (word), 0 does nothing
+static DMA_LENGTH: usize = 0x5;
+/// Byte a fill writes
+static DMA_VALUE: usize = 0x7;
+/// See DmaMode
+static DMA_MODE: usize = 0x8;
+/// Cycles per byte, the bus is busy for a read and a write or just a write
+static COPY_CYCLES: u64 = 2;
+static FILL_CYCLES: u64 = 1;
+
+#[derive(Debug, FromPrimitive, Clone, Copy)]
+/// Values of the command register
+enum DmaCommand {
+    /// copy length bytes from source to destination
+    Copy    = 0x01,
+    /// write the value to length bytes at destination
+    Fill    = 0x02,
+}
+
+#[repr(u8)]
+/// Bits of the mode register
+enum DmaMode {
+    // keep reading the same address, eg to drain an input register
+    FixedSource = 0b00000001,
+    // keep writing the same address, eg to feed the terminal
+    FixedDest   = 0b00000010,
+}
+
+#[derive(Clone)]
+/// Copies and fills memory while the CPU waits
+pub struct Dma {
+    regs: [u8; 16],
+    /// Cycles the CPU has to wait for the last transfer
+    stall: u64,
+    /// A transfer is running, it cant start another one through the registers
+    busy: bool,
+}
+
+impl Dma {
+    pub fn init() -> Dma {
+        Dma {
+            regs: [0; 16],
+            stall: 0,
+            busy: false,
+        }
+    }
+
+    fn word(&self, reg: usize) -> u16 {
+        self.regs[reg] as u16 | (self.regs[reg + 1] as u16) << 8
+    }
+
+    fn has(&self, mode: DmaMode) -> bool {
+        self.regs[DMA_MODE] & mode as u8 != 0
+    }
+
+    /// Cycles the CPU lost to transfers since the last call
+    pub fn take_stall(&mut self) -> u64 {
+        std::mem::take(&mut self.stall)
+    }
+}
+
+impl Bus {
+    pub fn read_dma(&self, addr: u16) -> u8 {
+        self.dma.regs[addr as usize - DMA_LOC]
+    }
+
+    /**
+    Write the DMA registers, writing the command register does the whole
+    transfer right away, the CPU gets stalled for it afterwards.
+    Everything goes through the bus, so IO registers and GPU arguments work.
+    Writes from a transfer to the DMA registers themselves get dropped,
+    otherwise a transfer over them would start itself again forever
+    */
+    pub fn write_dma(&mut self, addr: u16, byte: u8) {
+        if self.dma.busy {
+            return
+        }
+        let reg = addr as usize - DMA_LOC;
+        self.dma.regs[reg] = byte;
+        if reg != 0 {
+            return
+        }
+        let Some(cmd) = DmaCommand::from_u8(byte) else {
+            return
+        };
+        let mut src = self.dma.word(DMA_SOURCE);
+        let mut dest = self.dma.word(DMA_DEST);
+        let len = self.dma.word(DMA_LENGTH);
+        let value = self.dma.regs[DMA_VALUE];
+        let src_step = if self.dma.has(DmaMode::FixedSource) {0} else {1};
+        let dest_step = if self.dma.has(DmaMode::FixedDest) {0} else {1};
+        self.dma.busy = true;
+        for _ in 0..len {
+            // one byte at a time, so overlapping copies repeat a pattern
+            let byte = match cmd {
+                DmaCommand::Copy => self.read(src),
+                DmaCommand::Fill => value,
+            };
+            self.write(dest, byte);
+            src = src.wrapping_add(src_step);
+            dest = dest.wrapping_add(dest_step);
+        }
+        self.dma.busy = false;
+        let per_byte = match cmd {
+            DmaCommand::Copy => COPY_CYCLES,
+            DmaCommand::Fill => FILL_CYCLES,
+        };
+        self.dma.stall += len as u64 * per_byte;
+    }
+}
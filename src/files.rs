@@ -0,0 +1,360 @@
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
+
+use crate::Bus;
+
+/// Start of the file registers, writing here runs a FileCommand
+pub static FILES_LOC: usize = 0x4050;
+/// How the file gets opened, see FileMode
+static FILES_MODE: usize = 0x1;
+/// Pointer to the null terminated file name (word)
+static FILES_NAME: usize = 0x2;
+/// Where Read and Write copy to and from (word)
+static FILES_BUFFER: usize = 0x4;
+/// How many bytes Read and Write should copy, afterwards how many they did (word)
+static FILES_LENGTH: usize = 0x6;
+/// See FileStatus
+static FILES_STATUS: usize = 0x8;
+/// Reading gives the next byte of the file, writing adds one
+static FILES_DATA: usize = 0x9;
+/// Size of the file after Open, 32 bit little endian
+static FILES_SIZE: usize = 0xA;
+/// Longest file name thats read from memory
+static MAX_NAME: usize = 255;
+
+#[derive(Debug, FromPrimitive, Clone, Copy)]
+/// Values of the command register
+enum FileCommand {
+    /// open the file at the name pointer with the mode
+    Open    = 0x01,
+    Close   = 0x02,
+    /// copy up to length bytes of the file to the buffer
+    Read    = 0x03,
+    /// copy length bytes from the buffer to the end of the file
+    Write   = 0x04,
+    /// delete the file at the name pointer
+    Delete  = 0x05,
+}
+
+#[derive(Debug, FromPrimitive, Clone, Copy, PartialEq)]
+/// Values of the mode register
+enum FileMode {
+    Read    = 0x00,
+    /// empty the file, or make a new one
+    Write   = 0x01,
+    /// add to the end, or make a new one
+    Append  = 0x02,
+}
+
+#[repr(u8)]
+/// Values of the status register
+enum FileStatus {
+    Ok              = 0x00,
+    // the command byte isnt a FileCommand
+    UnknownCommand  = 0x01,
+    // theres no file open, or it was opened the other way
+    NotOpen         = 0x02,
+    NotFound        = 0x03,
+    // the name is empty or leaves the directory
+    BadName         = 0x04,
+    // anything else the host complained about
+    IoError         = 0x05,
+    // nothing left to read
+    EndOfFile       = 0x06,
+}
+
+#[derive(Clone)]
+/// The file thats open right now
+enum OpenFile {
+    None,
+    /// the whole file is read on open
+    Reading {data: Vec<u8>, pos: usize},
+    /// every write goes straight to the host, through the handle from Open
+    Writing {file: Arc<Mutex<fs::File>>},
+}
+
+#[derive(Clone)]
+/// Files from one host directory, the ROM cant get out of it
+pub struct Files {
+    /// Only there if a directory was given with --files
+    root: Option<PathBuf>,
+    regs: [u8; 16],
+    open: OpenFile,
+}
+
+impl Files {
+    pub fn init() -> Files {
+        Files {
+            root: None,
+            regs: [0; 16],
+            open: OpenFile::None,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.root.is_some()
+    }
+
+    /// Give ROMs access to this directory
+    pub fn set_root(&mut self, dir: &str) -> io::Result<()> {
+        self.root = Some(fs::canonicalize(dir)?);
+        Ok(())
+    }
+
+    fn word(&self, reg: usize) -> u16 {
+        self.regs[reg] as u16 | (self.regs[reg + 1] as u16) << 8
+    }
+
+    fn set_word(&mut self, reg: usize, value: u16) {
+        self.regs[reg..reg + 2].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn set_status(&mut self, status: FileStatus) {
+        self.regs[FILES_STATUS] = status as u8;
+    }
+
+    /**
+    Turn a name from the ROM into a path in the directory
+
+    Only plain relative names work, no .. or absolute paths, and symlinks
+    cant point outside either. The path thats returned has no symlinks left
+    in it, so opening it with open() cant end up anywhere else
+    */
+    fn resolve(&self, name: &str) -> Result<PathBuf, FileStatus> {
+        let root = self.root.as_ref().ok_or(FileStatus::NotOpen)?;
+        let relative = Path::new(name);
+        if name.is_empty() || !relative.components().all(|c| matches!(c, Component::Normal(_))) {
+            return Err(FileStatus::BadName)
+        }
+        let path = root.join(relative);
+        if let Ok(real) = fs::canonicalize(&path) {
+            return if real.starts_with(root) {Ok(real)} else {Err(FileStatus::BadName)}
+        }
+        // the file might not exist yet, so check where its directory really is
+        let name = path.file_name().ok_or(FileStatus::BadName)?;
+        let parent = path.parent().ok_or(FileStatus::BadName)?;
+        let parent = fs::canonicalize(parent).map_err(|_| FileStatus::NotFound)?;
+        if !parent.starts_with(root) {
+            return Err(FileStatus::BadName)
+        }
+        Ok(parent.join(name))
+    }
+
+    /// Next byte when reading a byte at a time
+    fn read_byte(&mut self) -> u8 {
+        let OpenFile::Reading {data, pos} = &mut self.open else {
+            self.set_status(FileStatus::NotOpen);
+            return 0
+        };
+        let Some(&byte) = data.get(*pos) else {
+            self.set_status(FileStatus::EndOfFile);
+            return 0
+        };
+        *pos += 1;
+        self.set_status(FileStatus::Ok);
+        byte
+    }
+
+    /// Add bytes to the file thats open for writing
+    fn append(&mut self, bytes: &[u8]) -> Result<(), FileStatus> {
+        let OpenFile::Writing {file} = &self.open else {
+            return Err(FileStatus::NotOpen)
+        };
+        file.lock().unwrap().write_all(bytes).map_err(|_| FileStatus::IoError)
+    }
+}
+
+/**
+Open a path from resolve
+
+The last part of the path isnt followed if its a symlink, so one that
+shows up after resolve (or points nowhere yet) cant send the file outside
+*/
+fn open(path: &Path, options: &mut fs::OpenOptions) -> io::Result<fs::File> {
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::custom_flags(options, libc::O_NOFOLLOW);
+    options.open(path)
+}
+
+/// Status for errors from the host
+fn io_status(e: io::Error) -> FileStatus {
+    match e.kind() {
+        io::ErrorKind::NotFound => FileStatus::NotFound,
+        _ => FileStatus::IoError
+    }
+}
+
+impl Bus {
+    /// Read the file registers, the data register reads from the file
+    pub fn read_files(&mut self, addr: u16) -> u8 {
+        let reg = addr as usize - FILES_LOC;
+        if reg == FILES_DATA {
+            return self.files.read_byte()
+        }
+        self.files.regs[reg]
+    }
+
+    /// Write the file registers, the command and data registers do something right away
+    pub fn write_files(&mut self, addr: u16, byte: u8) {
+        let reg = addr as usize - FILES_LOC;
+        if reg == FILES_DATA {
+            let status = match self.files.append(&[byte]) {
+                Ok(()) => FileStatus::Ok,
+                Err(status) => status
+            };
+            self.files.set_status(status);
+            return
+        }
+        self.files.regs[reg] = byte;
+        if reg == 0 {
+            let status = match self.run_file_command(byte) {
+                Ok(()) => FileStatus::Ok,
+                Err(status) => status
+            };
+            self.files.set_status(status);
+        }
+    }
+
+    /// Read the null terminated name the name pointer points to
+    fn file_name(&mut self) -> String {
+        let mut ptr = self.files.word(FILES_NAME);
+        let mut name = vec![];
+        while name.len() < MAX_NAME {
+            let byte = self.read(ptr);
+            if byte == 0 {
+                break
+            }
+            name.push(byte);
+            ptr = ptr.wrapping_add(1);
+        }
+        String::from_utf8_lossy(&name).into_owned()
+    }
+
+    fn run_file_command(&mut self, byte: u8) -> Result<(), FileStatus> {
+        let Some(cmd) = FileCommand::from_u8(byte) else {
+            return Err(FileStatus::UnknownCommand)
+        };
+        match cmd {
+            FileCommand::Open => {
+                // whatever was open before is closed even if this fails
+                self.files.open = OpenFile::None;
+                let name = self.file_name();
+                let path = self.files.resolve(&name)?;
+                let mode = FileMode::from_u8(self.files.regs[FILES_MODE]).unwrap_or(FileMode::Read);
+                let size = if mode == FileMode::Read {
+                    let mut data = vec![];
+                    open(&path, fs::OpenOptions::new().read(true))
+                        .and_then(|mut f| f.read_to_end(&mut data))
+                        .map_err(io_status)?;
+                    let size = data.len();
+                    self.files.open = OpenFile::Reading {data, pos: 0};
+                    size
+                } else {
+                    let file = open(&path, fs::OpenOptions::new().write(true).create(true)
+                        .truncate(mode == FileMode::Write).append(mode == FileMode::Append))
+                        .map_err(io_status)?;
+                    let size = file.metadata().map(|m| m.len() as usize).unwrap_or(0);
+                    self.files.open = OpenFile::Writing {file: Arc::new(Mutex::new(file))};
+                    size
+                };
+                self.files.regs[FILES_SIZE..FILES_SIZE + 4].copy_from_slice(&(size as u32).to_le_bytes());
+            }
+            FileCommand::Close => self.files.open = OpenFile::None,
+            FileCommand::Read => {
+                let want = self.files.word(FILES_LENGTH) as usize;
+                let OpenFile::Reading {data, pos} = &mut self.files.open else {
+                    return Err(FileStatus::NotOpen)
+                };
+                let bytes = data[*pos..(*pos + want).min(data.len())].to_vec();
+                *pos += bytes.len();
+                let len = bytes.len();
+                // straight into memory
+                let buffer = self.files.word(FILES_BUFFER);
+                for (i, byte) in bytes.into_iter().enumerate() {
+                    self.write(buffer.wrapping_add(i as u16), byte);
+                }
+                self.files.set_word(FILES_LENGTH, len as u16);
+                // asking for nothing is fine, only running out isnt
+                if len == 0 && want > 0 {
+                    return Err(FileStatus::EndOfFile)
+                }
+            }
+            FileCommand::Write => {
+                let buffer = self.files.word(FILES_BUFFER);
+                let len = self.files.word(FILES_LENGTH);
+                let bytes: Vec<u8> = (0..len).map(|i| self.read(buffer.wrapping_add(i))).collect();
+                self.files.append(&bytes)?;
+            }
+            FileCommand::Delete => {
+                let name = self.file_name();
+                let path = self.files.resolve(&name)?;
+                fs::remove_file(path).map_err(io_status)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Files with a fresh directory in the temp dir as the root
+    fn files(test: &str) -> Files {
+        let dir = std::env::temp_dir().join(format!("e6502-files-{}-{}", test, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        let mut files = Files::init();
+        files.set_root(dir.to_str().unwrap()).unwrap();
+        files
+    }
+
+    #[test]
+    fn resolve_rejects_parent_dirs() {
+        let files = files("parent");
+        for name in ["..", "../escape.txt", "sub/../../escape.txt", "sub/.."] {
+            assert!(matches!(files.resolve(name), Err(FileStatus::BadName)), "{}", name);
+        }
+    }
+
+    #[test]
+    fn resolve_rejects_absolute_paths() {
+        let files = files("absolute");
+        for name in ["/etc/passwd", "/", "/tmp/escape.txt"] {
+            assert!(matches!(files.resolve(name), Err(FileStatus::BadName)), "{}", name);
+        }
+    }
+
+    #[test]
+    fn resolve_rejects_empty_names() {
+        let files = files("empty");
+        assert!(matches!(files.resolve(""), Err(FileStatus::BadName)));
+    }
+
+    #[test]
+    fn resolve_keeps_plain_names_inside() {
+        let files = files("plain");
+        let root = files.root.clone().unwrap();
+        for name in ["save.txt", "sub/save.txt"] {
+            let path = files.resolve(name).unwrap_or_else(|_| panic!("{}", name));
+            assert!(path.starts_with(&root), "{}", name);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn resolve_rejects_symlinks_outside() {
+        let files = files("symlink");
+        let root = files.root.clone().unwrap();
+        std::os::unix::fs::symlink("/etc", root.join("out")).unwrap();
+        std::os::unix::fs::symlink("/etc/passwd", root.join("passwd")).unwrap();
+        for name in ["out/passwd", "passwd", "out/new.txt"] {
+            assert!(matches!(files.resolve(name), Err(FileStatus::BadName)), "{}", name);
+        }
+    }
+}
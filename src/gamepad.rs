@@ -0,0 +1,123 @@
+use crate::Bus;
+
+/// Controller buttons, see PadButton, low byte
+pub static PAD_LOC: usize = 0x6130;
+/// Controller status, bit 0 set if a host gamepad is used
+pub static PAD_STATUS_LOC: usize = 0x6132;
+/// Key map used without a gamepad, one raylib key code (word) per PadButton bit
+pub static PAD_KEYMAP_LOC: usize = 0x6140;
+/// Amount of buttons on the controller
+static PAD_BUTTONS: usize = 12;
+/// How far the left stick has to move to count as the d-pad
+static PAD_DEADZONE: f32 = 0.5;
+
+#[repr(u16)]
+#[derive(Clone, Copy)]
+/// Bits of the controller register
+enum PadButton {
+    Up      = 0b0000000000000001,
+    Down    = 0b0000000000000010,
+    Left    = 0b0000000000000100,
+    Right   = 0b0000000000001000,
+    A       = 0b0000000000010000,
+    B       = 0b0000000000100000,
+    Select  = 0b0000000001000000,
+    Start   = 0b0000000010000000,
+    X       = 0b0000000100000000,
+    Y       = 0b0000001000000000,
+    L       = 0b0000010000000000,
+    R       = 0b0000100000000000,
+}
+
+/// The buttons in bit order, with the raylib gamepad button they come from
+static PAD_MAPPING: [(PadButton, i32); PAD_BUTTONS] = [
+    (PadButton::Up, 1),      // GAMEPAD_BUTTON_LEFT_FACE_UP
+    (PadButton::Down, 3),    // GAMEPAD_BUTTON_LEFT_FACE_DOWN
+    (PadButton::Left, 4),    // GAMEPAD_BUTTON_LEFT_FACE_LEFT
+    (PadButton::Right, 2),   // GAMEPAD_BUTTON_LEFT_FACE_RIGHT
+    (PadButton::A, 7),       // GAMEPAD_BUTTON_RIGHT_FACE_DOWN
+    (PadButton::B, 6),       // GAMEPAD_BUTTON_RIGHT_FACE_RIGHT
+    (PadButton::Select, 13), // GAMEPAD_BUTTON_MIDDLE_LEFT
+    (PadButton::Start, 15),  // GAMEPAD_BUTTON_MIDDLE_RIGHT
+    (PadButton::X, 8),       // GAMEPAD_BUTTON_RIGHT_FACE_LEFT
+    (PadButton::Y, 5),       // GAMEPAD_BUTTON_RIGHT_FACE_UP
+    (PadButton::L, 9),       // GAMEPAD_BUTTON_LEFT_TRIGGER_1
+    (PadButton::R, 11),      // GAMEPAD_BUTTON_RIGHT_TRIGGER_1
+];
+
+/// Keys used when theres no gamepad, same order as PAD_MAPPING
+static DEFAULT_KEYMAP: [u16; PAD_BUTTONS] = [
+    265, // up arrow
+    264, // down arrow
+    263, // left arrow
+    262, // right arrow
+    90,  // Z
+    88,  // X
+    344, // right shift
+    257, // enter
+    65,  // A
+    83,  // S
+    81,  // Q
+    87,  // W
+];
+
+impl Bus {
+    /// Put the default keys into the key map
+    pub fn load_default_keymap(&mut self) {
+        for (i, key) in DEFAULT_KEYMAP.iter().enumerate() {
+            let base = PAD_KEYMAP_LOC - 0x6000 + i * 2;
+            self.gpu[base] = (key & 0xff) as u8;
+            self.gpu[base + 1] = (key >> 8) as u8;
+        }
+    }
+
+    /// Raylib key code mapped to button i
+    fn mapped_key(&self, i: usize) -> i32 {
+        let base = PAD_KEYMAP_LOC - 0x6000 + i * 2;
+        (self.gpu[base + 1] as i32) << 8 | self.gpu[base] as i32
+    }
+
+    /// Get the state of all buttons, from the first gamepad or the key map
+    fn pad_buttons(&self) -> u16 {
+        let mut buttons = 0;
+        unsafe {
+            if raylib::ffi::IsGamepadAvailable(0) {
+                for (button, pad_button) in PAD_MAPPING {
+                    if raylib::ffi::IsGamepadButtonDown(0, pad_button) {
+                        buttons |= button as u16;
+                    }
+                }
+                // the left stick works as a d-pad too
+                let x = raylib::ffi::GetGamepadAxisMovement(0, 0);
+                let y = raylib::ffi::GetGamepadAxisMovement(0, 1);
+                if x < -PAD_DEADZONE { buttons |= PadButton::Left as u16; }
+                if x > PAD_DEADZONE { buttons |= PadButton::Right as u16; }
+                if y < -PAD_DEADZONE { buttons |= PadButton::Up as u16; }
+                if y > PAD_DEADZONE { buttons |= PadButton::Down as u16; }
+            } else {
+                for (i, (button, _)) in PAD_MAPPING.iter().enumerate() {
+                    if raylib::ffi::IsKeyDown(self.mapped_key(i)) {
+                        buttons |= *button as u16;
+                    }
+                }
+            }
+        }
+        buttons
+    }
+
+    /// Read the controller registers
+    pub fn read_gamepad(&mut self, addr: u16) -> u8 {
+        // no window, no input
+        if !self.gpu_enable {
+            return 0
+        }
+        match addr as usize {
+            a if a == PAD_LOC => (self.pad_buttons() & 0xff) as u8,
+            a if a == PAD_LOC + 1 => (self.pad_buttons() >> 8) as u8,
+            a if a == PAD_STATUS_LOC => unsafe {
+                if raylib::ffi::IsGamepadAvailable(0) {0x01} else {0x00}
+            },
+            _ => 0
+        }
+    }
+}
@@ -0,0 +1,435 @@
+use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
+
+use crate::Bus;
+
+/// Version of the GPU protocol, bumped whenever commands or registers get added
+pub static GPU_VERSION: u8 = 6;
+/// Writing a GpuCommand here runs it
+pub static GPU_RUN_LOC: usize = 0x6000;
+/// Result of IsKeyDown, 1 if the key is down
+pub static GPU_KEY_DOWN_LOC: usize = 0x6100;
+/// GPU mode register
+pub static GPU_MODE_LOC: usize = 0x6101;
+/// Mode bit to make coordinates and sizes words instead of bytes
+pub static GPU_MODE_WIDE: u8 = 0b00000001;
+/// Protocol version register, read only
+pub static GPU_VERSION_LOC: usize = 0x6102;
+/// Capability flags low byte, read only
+pub static GPU_CAPS_LOC: usize = 0x6103;
+/// Status of the last command, see GpuStatus
+pub static GPU_STATUS_LOC: usize = 0x6105;
+/// The command byte that caused the last error
+pub static GPU_ERR_CMD_LOC: usize = 0x6106;
+/// Mouse x in logical pixels, low byte
+pub static MOUSE_X_LOC: usize = 0x6110;
+/// Mouse y in logical pixels, low byte
+pub static MOUSE_Y_LOC: usize = 0x6112;
+/// Mouse buttons, see MouseButton
+pub static MOUSE_BTN_LOC: usize = 0x6114;
+/// Mouse wheel movement this frame, signed
+pub static MOUSE_WHEEL_LOC: usize = 0x6115;
+
+#[repr(u16)]
+/// Bits of the capability register, so a ROM can check what the GPU supports
+pub enum GpuCaps {
+    // Sprite attribute table, patterns and collision status
+    Sprites     = 0b0000000000000001,
+    // Palette and the indexed colour commands
+    Palette     = 0b0000000000000010,
+    // Wide coordinate mode
+    WideCoords  = 0b0000000000000100,
+    // DrawPixel, DrawRectangleLines, DrawCircle(Lines), DrawTriangle, DrawText
+    Primitives  = 0b0000000000001000,
+    // Mouse registers
+    Mouse       = 0b0000000000010000,
+    // Key event and character queues
+    KeyQueue    = 0b0000000000100000,
+    // Controller register
+    Gamepad     = 0b0000000001000000,
+    // Frame counter and vblank registers
+    VSync       = 0b0000000010000000,
+    // DefineTexture, DrawTexture, DrawTile
+    Textures    = 0b0000000100000000,
+}
+
+/// Everything this GPU supports
+static GPU_CAPS: u16 = GpuCaps::Sprites as u16 | GpuCaps::Palette as u16 |
+    GpuCaps::WideCoords as u16 | GpuCaps::Primitives as u16 | GpuCaps::Mouse as u16 |
+    GpuCaps::KeyQueue as u16 | GpuCaps::Gamepad as u16 | GpuCaps::VSync as u16 |
+    GpuCaps::Textures as u16;
+
+#[repr(u8)]
+/// Values of the status register
+pub enum GpuStatus {
+    // last command ran fine
+    Ok              = 0x00,
+    // the command byte isnt a GpuCommand, see GPU_ERR_CMD_LOC
+    UnknownCommand  = 0x01,
+    // a command was written but the ROM didnt enable the GPU
+    Disabled        = 0x02,
+    // the command got an argument it cant use
+    BadArgument     = 0x03,
+}
+
+#[repr(u8)]
+/// Bits of the mouse button register
+enum MouseButton {
+    Left    = 0b00000001,
+    Right   = 0b00000010,
+    Middle  = 0b00000100,
+}
+
+#[repr(u8)]
+#[derive(Debug, FromPrimitive, Clone, Copy)]
+/// Commands that can be written to $6000, arguments start at $6001
+pub enum GpuCommand {
+    /// start a new frame
+    BeginDrawing        = 0xBD,
+    /// draw sprites, present the frame and wait for the next one
+    EndDrawing          = 0xED,
+    /// r, g, b
+    ClearBackground     = 0xCB,
+    /// x, y, w, h, r, g, b
+    DrawRectangle       = 0xD5,
+    /// key, result goes to $6100
+    IsKeyDown           = 0xDE,
+    /// x1, y1, x2, y2, r, g, b
+    DrawLine            = 0xD1,
+    /// palette index
+    ClearBackgroundI    = 0xCC,
+    /// x, y, w, h, palette index
+    DrawRectangleI      = 0xD6,
+    /// x1, y1, x2, y2, palette index
+    DrawLineI           = 0xD2,
+    /// x, y, r, g, b
+    DrawPixel           = 0xD8,
+    /// x, y, w, h, r, g, b
+    DrawRectangleLines  = 0xD7,
+    /// x, y, radius, r, g, b
+    DrawCircle          = 0xDC,
+    /// x, y, radius, r, g, b
+    DrawCircleLines     = 0xDD,
+    /// x1, y1, x2, y2, x3, y3, r, g, b
+    DrawTriangle        = 0xD3,
+    /// string pointer (word), x, y, font size, r, g, b
+    DrawText            = 0xDA,
+    /// slot, bitmap pointer (word), w, h, bits per pixel, palette base, flags
+    DefineTexture       = 0xE0,
+    /// slot, x, y, scale, flags
+    DrawTexture         = 0xE1,
+    /// slot, tile, tile w, tile h, x, y, scale, flags
+    DrawTile            = 0xE2,
+}
+
+impl Bus {
+    /// Put the read only GPU registers in place
+    pub fn init_gpu_registers(&mut self) {
+        self.gpu[GPU_VERSION_LOC - 0x6000] = GPU_VERSION;
+        self.gpu[GPU_CAPS_LOC - 0x6000] = (GPU_CAPS & 0xff) as u8;
+        self.gpu[GPU_CAPS_LOC + 1 - 0x6000] = (GPU_CAPS >> 8) as u8;
+    }
+
+    /// Set the status register
+    pub fn set_gpu_status(&mut self, status: GpuStatus) {
+        self.gpu[GPU_STATUS_LOC - 0x6000] = status as u8;
+    }
+
+    /**
+    Read one of the mouse registers
+
+    Position is divided by the scale, so it matches the coordinates used for drawing
+    */
+    pub fn read_mouse(&mut self, addr: u16) -> u8 {
+        // theres no window to get the mouse from
+        if !self.gpu_enable {
+            return 0
+        }
+        let scale = self.gpu_scale as i32;
+        let addr = addr as usize;
+        unsafe {
+            // the picture might be scaled again to fit the window
+            let (mouse_x, mouse_y) = self.window.map_mouse(raylib::ffi::GetMouseX(), 
+                raylib::ffi::GetMouseY());
+            if addr == MOUSE_X_LOC || addr == MOUSE_X_LOC + 1 {
+                let x = (mouse_x / scale).max(0) as u16;
+                return if addr == MOUSE_X_LOC {(x & 0xff) as u8} else {(x >> 8) as u8}
+            }
+            if addr == MOUSE_Y_LOC || addr == MOUSE_Y_LOC + 1 {
+                let y = (mouse_y / scale).max(0) as u16;
+                return if addr == MOUSE_Y_LOC {(y & 0xff) as u8} else {(y >> 8) as u8}
+            }
+            if addr == MOUSE_BTN_LOC {
+                // raylib numbers the buttons left, right, middle
+                let mut buttons = 0;
+                for (i, button) in [MouseButton::Left, MouseButton::Right, MouseButton::Middle]
+                    .into_iter().enumerate() {
+                    if raylib::ffi::IsMouseButtonDown(i as i32) {
+                        buttons |= button as u8;
+                    }
+                }
+                return buttons
+            }
+            if addr == MOUSE_WHEEL_LOC {
+                // clamp so fast scrolling doesnt flip the sign
+                let wheel = raylib::ffi::GetMouseWheelMove().round().clamp(-128.0, 127.0);
+                return wheel as i8 as u8
+            }
+        }
+        0
+    }
+
+    /// Read the next GPU argument and move past it
+    pub fn next_arg(&mut self) -> u8 {
+        let byte = self.read(self.gpu_arg);
+        self.gpu_arg = self.gpu_arg.wrapping_add(1);
+        byte
+    }
+
+    /**
+    Read the next coordinate or size from the GPU arguments,
+    a byte normally or a little endian word in wide mode
+    */
+    pub fn next_coord(&mut self) -> i32 {
+        let low = self.next_arg() as i32;
+        if self.gpu[GPU_MODE_LOC - 0x6000] & GPU_MODE_WIDE == 0 {
+            return low
+        }
+        let high = self.next_arg() as i32;
+        high << 8 | low
+    }
+
+    /// Read an r, g, b colour from the next 3 GPU arguments
+    pub fn next_colour(&mut self) -> raylib::ffi::Color {
+        let r = self.next_arg();
+        let g = self.next_arg();
+        let b = self.next_arg();
+        raylib::ffi::Color {r, g, b, a: 0xFF}
+    }
+
+    /**
+    Run the command written to $6000 with the arguments at $6001+
+
+    Unknown commands dont do anything except setting the status register
+    */
+    pub fn run_gpu_command(&mut self, byte: u8) {
+        let Some(cmd) = GpuCommand::from_u8(byte) else {
+            self.set_gpu_status(GpuStatus::UnknownCommand);
+            self.gpu[GPU_ERR_CMD_LOC - 0x6000] = byte;
+            return
+        };
+        self.set_gpu_status(GpuStatus::Ok);
+        // arguments get read from the start again
+        self.gpu_arg = 0x6001;
+        match cmd {
+            GpuCommand::BeginDrawing => {
+                // tell raylib to start a new frame?
+                // TODO: look up what this does
+                self.begin_frame();
+            }
+            GpuCommand::EndDrawing => {
+                // tell raylib to handle 
+                // drawing the frame and waiting 
+                // for the next frame
+                // sprites go on top of everything else
+                self.draw_sprites();
+                self.draw_lcd();
+                self.end_frame();
+                // raylib updated its input queues, so move them over
+                self.keyboard.poll();
+                self.vsync.end_drawing();
+            }
+            GpuCommand::ClearBackground => {
+                // get colour components
+                let col = self.next_colour();
+                unsafe {
+                    // run command
+                    raylib::ffi::ClearBackground(col);
+                }
+            }
+            GpuCommand::DrawRectangle => {
+                // read rectangle position and size
+                let x = self.next_coord(); 
+                let y = self.next_coord();
+                let w = self.next_coord();
+                let h = self.next_coord();
+                // get colour components
+                let col = self.next_colour();
+                unsafe {
+                    // run command, scaled 
+                    raylib::ffi::DrawRectangle(x * self.gpu_scale as i32, 
+                        y * self.gpu_scale as i32, 
+                        w * self.gpu_scale as i32, 
+                        h * self.gpu_scale as i32, 
+                        col);
+                }
+            }
+            GpuCommand::IsKeyDown => {
+                // get the key the program wants to know
+                let key = self.next_arg();
+                unsafe {
+                    // is the key requested down?
+                    let is_down = raylib::ffi::IsKeyDown(key as i32);
+                    // set whether key is down or not
+                    self.gpu[GPU_KEY_DOWN_LOC - GPU_RUN_LOC] = if is_down {0x01} else {0x0};
+                }
+            }
+            GpuCommand::DrawLine => {
+                // p1 
+                let startx = self.next_coord();
+                let starty = self.next_coord();
+                // p2
+                let endx   = self.next_coord();
+                let endy   = self.next_coord();
+                // get colour components
+                let col = self.next_colour();
+                unsafe {
+                    // also scaled 
+                    raylib::ffi::DrawLine(startx * self.gpu_scale as i32,
+                        starty * self.gpu_scale as i32,
+                        endx * self.gpu_scale as i32,
+                        endy * self.gpu_scale as i32,
+                        col);
+                }
+            }
+            GpuCommand::ClearBackgroundI => {
+                let index = self.next_arg();
+                let col = self.palette_colour(index);
+                unsafe {
+                    raylib::ffi::ClearBackground(col);
+                }
+            }
+            GpuCommand::DrawRectangleI => {
+                // read rectangle position and size
+                let x = self.next_coord(); 
+                let y = self.next_coord();
+                let w = self.next_coord();
+                let h = self.next_coord();
+                // look up the colour
+                let index = self.next_arg();
+                let col = self.palette_colour(index);
+                unsafe {
+                    raylib::ffi::DrawRectangle(x * self.gpu_scale as i32, 
+                        y * self.gpu_scale as i32, 
+                        w * self.gpu_scale as i32, 
+                        h * self.gpu_scale as i32, 
+                        col);
+                }
+            }
+            GpuCommand::DrawLineI => {
+                // p1 
+                let startx = self.next_coord();
+                let starty = self.next_coord();
+                // p2
+                let endx   = self.next_coord();
+                let endy   = self.next_coord();
+                // look up the colour
+                let index = self.next_arg();
+                let col = self.palette_colour(index);
+                unsafe {
+                    raylib::ffi::DrawLine(startx * self.gpu_scale as i32,
+                        starty * self.gpu_scale as i32,
+                        endx * self.gpu_scale as i32,
+                        endy * self.gpu_scale as i32,
+                        col);
+                }
+            }
+            GpuCommand::DrawPixel => {
+                let x = self.next_coord();
+                let y = self.next_coord();
+                let col = self.next_colour();
+                let scale = self.gpu_scale as i32;
+                unsafe {
+                    // one pixel is scale x scale big on screen
+                    raylib::ffi::DrawRectangle(x * scale, y * scale, scale, scale, col);
+                }
+            }
+            GpuCommand::DrawRectangleLines => {
+                let x = self.next_coord();
+                let y = self.next_coord();
+                let w = self.next_coord();
+                let h = self.next_coord();
+                let col = self.next_colour();
+                let scale = self.gpu_scale as i32;
+                unsafe {
+                    raylib::ffi::DrawRectangleLines(x * scale, y * scale, 
+                        w * scale, h * scale, col);
+                }
+            }
+            GpuCommand::DrawCircle | GpuCommand::DrawCircleLines => {
+                // center and radius
+                let cx = self.next_coord();
+                let cy = self.next_coord();
+                let radius = self.next_coord() as f32;
+                let col = self.next_colour();
+                let scale = self.gpu_scale as i32;
+                unsafe {
+                    if matches!(cmd, GpuCommand::DrawCircle) {
+                        raylib::ffi::DrawCircle(cx * scale, cy * scale, 
+                            radius * scale as f32, col);
+                    } else {
+                        raylib::ffi::DrawCircleLines(cx * scale, cy * scale, 
+                            radius * scale as f32, col);
+                    }
+                }
+            }
+            GpuCommand::DrawTriangle => {
+                let scale = self.gpu_scale as f32;
+                // read the 3 points
+                let mut points = [raylib::ffi::Vector2 {x: 0.0, y: 0.0}; 3];
+                for point in points.iter_mut() {
+                    point.x = self.next_coord() as f32 * scale;
+                    point.y = self.next_coord() as f32 * scale;
+                }
+                let col = self.next_colour();
+                // raylib only draws counter-clockwise triangles, which with
+                // y going down means a negative cross product, so flip the rest
+                let [a, b, c] = points;
+                let cross = (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x);
+                unsafe {
+                    if cross < 0.0 {
+                        raylib::ffi::DrawTriangle(a, b, c, col);
+                    } else {
+                        raylib::ffi::DrawTriangle(a, c, b, col);
+                    }
+                }
+            }
+            GpuCommand::DrawText => {
+                // pointer to the null terminated string
+                let ptr = self.next_arg() as u16 | (self.next_arg() as u16) << 8;
+                let x = self.next_coord();
+                let y = self.next_coord();
+                let size = self.next_arg() as i32;
+                let col = self.next_colour();
+                let scale = self.gpu_scale as i32;
+                // copy the string, raylib needs the null terminator
+                let mut text: Vec<i8> = vec![];
+                let mut i = ptr;
+                while self.read(i) != 0x00 {
+                    text.push(self.read(i) as i8);
+                    i = i.wrapping_add(1);
+                }
+                text.push(0);
+                unsafe {
+                    raylib::ffi::DrawText(text.as_ptr(), x * scale, y * scale, 
+                        size * scale, col);
+                }
+            }
+            GpuCommand::DefineTexture => {
+                if let Err(status) = self.define_texture() {
+                    self.set_gpu_status(status);
+                }
+            }
+            GpuCommand::DrawTexture => {
+                if let Err(status) = self.draw_texture() {
+                    self.set_gpu_status(status);
+                }
+            }
+            GpuCommand::DrawTile => {
+                if let Err(status) = self.draw_tile() {
+                    self.set_gpu_status(status);
+                }
+            }
+        }
+    }
+}
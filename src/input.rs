@@ -0,0 +1,99 @@
+use std::io::{BufReader, Read};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+
+/// Where the bytes for $4001 come from
+pub enum Input {
+    /// Keys from the terminal in raw mode, through crossterm
+    Terminal,
+    /// Bytes from a pipe or a file, read by a background thread.
+    /// ended is set once the last byte came through
+    Stream {rx: Receiver<u8>, ended: bool},
+}
+
+impl Input {
+    /// Read bytes in the background so the emulator never blocks on them
+    pub fn stream<R: Read + Send + 'static>(reader: R) -> Input {
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for byte in BufReader::new(reader).bytes() {
+                // stop at the end or if the emulator is gone
+                let Ok(byte) = byte else { break };
+                if tx.send(byte).is_err() {
+                    break
+                }
+            }
+        });
+        Input::Stream {rx, ended: false}
+    }
+
+    /// Next byte of a stream, if there is one yet
+    pub fn next(&mut self) -> Option<u8> {
+        match self {
+            Input::Terminal => None,
+            Input::Stream {rx, ended} => match rx.try_recv() {
+                Ok(byte) => Some(byte),
+                Err(TryRecvError::Empty) => None,
+                // the reader thread is done
+                Err(TryRecvError::Disconnected) => {
+                    *ended = true;
+                    None
+                }
+            },
+        }
+    }
+
+    /// Whether a stream has no bytes left, the terminal never ends
+    pub fn ended(&self) -> bool {
+        matches!(self, Input::Stream {ended: true, ..})
+    }
+}
+
+/**
+Turn a key from the terminal into the bytes a ROM sees at $4001
+
+Keys without an ASCII code become the escape sequences an xterm sends,
+so ROMs can handle them like a real serial terminal. Ctrl+letter gives the
+control character and Alt puts an escape in front. Returns nothing for
+keys that arent mapped
+*/
+pub fn key_bytes(key: KeyEvent) -> Vec<u8> {
+    // some platforms also report releases
+    if key.kind == KeyEventKind::Release {
+        return Vec::new()
+    }
+    let mut bytes = match key.code {
+        // ctrl+a is 1 up to ctrl+z which is 26
+        KeyCode::Char(c) if key.modifiers.contains(KeyModifiers::CONTROL) && c.is_ascii_alphabetic() =>
+            vec![c.to_ascii_lowercase() as u8 - b'a' + 1],
+        KeyCode::Char(c) => vec![c as u8],
+        KeyCode::Enter => vec![10],
+        KeyCode::Backspace => vec![8],
+        KeyCode::Tab => vec![9],
+        KeyCode::Esc => vec![27],
+        KeyCode::BackTab => b"\x1b[Z".to_vec(),
+        KeyCode::Up => b"\x1b[A".to_vec(),
+        KeyCode::Down => b"\x1b[B".to_vec(),
+        KeyCode::Right => b"\x1b[C".to_vec(),
+        KeyCode::Left => b"\x1b[D".to_vec(),
+        KeyCode::Home => b"\x1b[H".to_vec(),
+        KeyCode::End => b"\x1b[F".to_vec(),
+        KeyCode::Insert => b"\x1b[2~".to_vec(),
+        KeyCode::Delete => b"\x1b[3~".to_vec(),
+        KeyCode::PageUp => b"\x1b[5~".to_vec(),
+        KeyCode::PageDown => b"\x1b[6~".to_vec(),
+        KeyCode::F(n @ 1..=4) => vec![27, b'O', b'P' + n - 1],
+        KeyCode::F(n @ 5..=12) => {
+            // the numbers skip 16 and 22 for historical reasons
+            let code = [15, 17, 18, 19, 20, 21, 23, 24][n as usize - 5];
+            format!("\x1b[{}~", code).into_bytes()
+        }
+        _ => return Vec::new()
+    };
+    if key.modifiers.contains(KeyModifiers::ALT) {
+        bytes.insert(0, 27);
+    }
+    bytes
+}
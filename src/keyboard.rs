@@ -0,0 +1,150 @@
+use std::collections::VecDeque;
+
+use crate::Bus;
+
+/// Amount of key events in the queue, read only
+pub static KEY_EVENT_COUNT_LOC: usize = 0x6120;
+/// Reading pops the next key event and returns its KeyEventType
+pub static KEY_EVENT_LOC: usize = 0x6121;
+/// Key code of the last popped event, low byte
+pub static KEY_CODE_LOC: usize = 0x6122;
+/// Key code of the last popped event, high byte
+pub static KEY_CODE_HIGH_LOC: usize = 0x6123;
+/// Amount of typed characters in the queue, read only
+pub static CHAR_COUNT_LOC: usize = 0x6124;
+/// Reading pops the next typed character, 0 if there is none
+pub static CHAR_LOC: usize = 0x6125;
+/// Which queues raise an IRQ when they arent empty, see KeyIrq
+pub static KEY_IRQ_LOC: usize = 0x6126;
+/// How many events or characters fit in a queue before new ones get dropped
+static KEY_QUEUE_SIZE: usize = 32;
+
+#[repr(u8)]
+#[derive(Clone, Copy)]
+/// What happened to a key
+pub enum KeyEventType {
+    // the queue was empty
+    None        = 0x00,
+    Pressed     = 0x01,
+    Released    = 0x02,
+}
+
+#[repr(u8)]
+/// Bits of the IRQ enable register
+enum KeyIrq {
+    // IRQ while there are key events
+    Events  = 0b00000001,
+    // IRQ while there are typed characters
+    Chars   = 0b00000010,
+}
+
+#[derive(Clone)]
+/// Key event and character queues filled from the raylib window
+pub struct Keyboard {
+    /// Key code and what happened to it
+    events: VecDeque<(u16, KeyEventType)>,
+    /// Typed characters
+    chars: VecDeque<u8>,
+    /// Keys that were pressed but not released yet
+    down: Vec<i32>,
+    /// Key code of the last popped event
+    code: u16,
+    /// IRQ enable bits
+    irq_enable: u8,
+}
+
+impl Keyboard {
+    pub fn init() -> Keyboard {
+        Keyboard {
+            events: VecDeque::new(),
+            chars: VecDeque::new(),
+            down: vec![],
+            code: 0,
+            irq_enable: 0,
+        }
+    }
+
+    /// Add an event, dropped if the queue is full
+    fn push_event(&mut self, key: i32, kind: KeyEventType) {
+        if self.events.len() < KEY_QUEUE_SIZE {
+            self.events.push_back((key as u16, kind));
+        }
+    }
+
+    /**
+    Get new key presses, releases and characters from raylib
+
+    raylib only updates these once per frame, so this runs after EndDrawing
+    */
+    pub fn poll(&mut self) {
+        unsafe {
+            // first the keys that got let go since last time
+            let mut i = 0;
+            while i < self.down.len() {
+                let key = self.down[i];
+                if !raylib::ffi::IsKeyDown(key) {
+                    self.push_event(key, KeyEventType::Released);
+                    self.down.remove(i);
+                } else {
+                    i += 1;
+                }
+            }
+            // GetKeyPressed returns 0 once the raylib queue is empty
+            loop {
+                let key = raylib::ffi::GetKeyPressed();
+                if key == 0 {
+                    break
+                }
+                self.push_event(key, KeyEventType::Pressed);
+                if !self.down.contains(&key) {
+                    self.down.push(key);
+                }
+            }
+            // same for characters, these are unicode so only keep what fits in a byte
+            loop {
+                let c = raylib::ffi::GetCharPressed();
+                if c == 0 {
+                    break
+                }
+                if c <= 0xFF && self.chars.len() < KEY_QUEUE_SIZE {
+                    self.chars.push_back(c as u8);
+                }
+            }
+        }
+    }
+
+    /// Whether the keyboard wants an interrupt
+    pub fn irq(&self) -> bool {
+        (self.irq_enable & KeyIrq::Events as u8 != 0 && !self.events.is_empty()) ||
+            (self.irq_enable & KeyIrq::Chars as u8 != 0 && !self.chars.is_empty())
+    }
+}
+
+impl Bus {
+    /// Read one of the keyboard queue registers
+    pub fn read_keyboard(&mut self, addr: u16) -> u8 {
+        let kb = &mut self.keyboard;
+        match addr as usize {
+            a if a == KEY_EVENT_COUNT_LOC => kb.events.len() as u8,
+            a if a == KEY_EVENT_LOC => {
+                // pop and remember the key code for the next 2 registers
+                let (code, kind) = kb.events.pop_front().unwrap_or((0, KeyEventType::None));
+                kb.code = code;
+                kind as u8
+            }
+            a if a == KEY_CODE_LOC => (kb.code & 0xff) as u8,
+            a if a == KEY_CODE_HIGH_LOC => (kb.code >> 8) as u8,
+            a if a == CHAR_COUNT_LOC => kb.chars.len() as u8,
+            a if a == CHAR_LOC => kb.chars.pop_front().unwrap_or(0),
+            a if a == KEY_IRQ_LOC => kb.irq_enable,
+            _ => 0
+        }
+    }
+
+    /// Write to the keyboard registers, only the IRQ enable is writable
+    pub fn write_keyboard(&mut self, addr: u16, byte: u8) {
+        if addr as usize == KEY_IRQ_LOC {
+            self.keyboard.irq_enable = byte;
+        }
+    }
+}
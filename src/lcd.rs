@@ -0,0 +1,411 @@
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use crate::Bus;
+
+/// Visible characters per line
+static LCD_COLUMNS: u8 = 16;
+/// Characters per line in DDRAM, the display shows a window of it
+static LCD_LINE_LEN: u8 = 40;
+/// How many cycles to wait between checking if the terminal needs a redraw
+static LCD_POLL_CYCLES: u64 = 1000;
+/// Dont redraw the terminal more often than this
+static LCD_REDRAW: Duration = Duration::from_millis(33);
+/// Pixels per character in the window, plus a gap of 1
+static LCD_CHAR_W: i32 = 5;
+static LCD_CHAR_H: i32 = 8;
+
+#[repr(u8)]
+/// Port A pins of the VIA the LCD control lines are connected to, like on Ben Eaters computer.
+/// The data lines are on port B, D4-D7 on PB4-PB7 in 4 bit mode
+enum LcdPin {
+    // register select, 0 for commands and 1 for data
+    Rs  = 0b00100000,
+    // 1 to read from the LCD
+    Rw  = 0b01000000,
+    // enable, the LCD takes the data when it goes low
+    E   = 0b10000000,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+/// Where the LCD gets shown
+pub enum LcdMode {
+    Off,
+    /// In the top right of the terminal
+    Terminal,
+    /// In the top left of the GPU window
+    Window,
+}
+
+#[derive(Clone)]
+/// HD44780 16x2 character LCD
+pub struct Lcd {
+    pub mode: LcdMode,
+    /// Characters, line 1 starts at $00 and line 2 at $40
+    ddram: [u8; 128],
+    /// 8 custom characters of 8 rows
+    cgram: [u8; 64],
+    /// Address counter
+    ac: u8,
+    /// The address counter points into CGRAM instead of DDRAM
+    in_cgram: bool,
+    /// Entry mode, the address counts up
+    increment: bool,
+    /// Entry mode, the display moves on every write
+    shift_on_write: bool,
+    display_on: bool,
+    cursor_on: bool,
+    blink_on: bool,
+    /// 8 bit interface, otherwise everything goes in 2 nibbles
+    eight_bit: bool,
+    two_lines: bool,
+    /// How far the display is moved left
+    shift: u8,
+    /// High nibble waiting for the low one in 4 bit mode
+    nibble: Option<u8>,
+    /// The next read in 4 bit mode gives the low nibble
+    read_low: bool,
+    /// Level of E, the LCD does things on its edges
+    last_e: bool,
+    /// Something changed since the last terminal redraw
+    dirty: bool,
+    idle: u64,
+    last_draw: Instant,
+}
+
+impl Lcd {
+    /// State after power on, 8 bit 1 line with the display off
+    pub fn init() -> Lcd {
+        Lcd {
+            mode: LcdMode::Off,
+            ddram: [0x20; 128],
+            cgram: [0; 64],
+            ac: 0,
+            in_cgram: false,
+            increment: true,
+            shift_on_write: false,
+            display_on: false,
+            cursor_on: false,
+            blink_on: false,
+            eight_bit: true,
+            two_lines: false,
+            shift: 0,
+            nibble: None,
+            read_low: false,
+            last_e: false,
+            dirty: true,
+            idle: 0,
+            last_draw: Instant::now(),
+        }
+    }
+
+    /// Move the address counter, DDRAM lines wrap into each other
+    fn step(&mut self, up: bool) {
+        if self.in_cgram {
+            self.ac = if up {self.ac.wrapping_add(1)} else {self.ac.wrapping_sub(1)} & 0x3F;
+            return
+        }
+        let ac = if up {self.ac.wrapping_add(1)} else {self.ac.wrapping_sub(1)};
+        self.ac = if self.two_lines {
+            match ac {
+                0x28 => 0x40,
+                0x68 => 0x00,
+                0x3F => 0x27,
+                0xFF => 0x67,
+                a => a
+            }
+        } else {
+            match ac {
+                0x50 => 0x00,
+                0xFF => 0x4F,
+                a => a
+            }
+        };
+    }
+
+    /// Move the display left or right by one character
+    fn shift_display(&mut self, left: bool) {
+        self.shift = if left {(self.shift + 1) % LCD_LINE_LEN} else {(self.shift + LCD_LINE_LEN - 1) % LCD_LINE_LEN};
+    }
+
+    fn command(&mut self, cmd: u8) {
+        match cmd.leading_zeros() {
+            // 0 doesnt do anything
+            8 => return,
+            // clear display
+            7 => {
+                self.ddram = [0x20; 128];
+                self.ac = 0;
+                self.in_cgram = false;
+                self.increment = true;
+                self.shift = 0;
+            }
+            // return home
+            6 => {
+                self.ac = 0;
+                self.in_cgram = false;
+                self.shift = 0;
+            }
+            // entry mode set
+            5 => {
+                self.increment = cmd & 0b10 != 0;
+                self.shift_on_write = cmd & 0b01 != 0;
+            }
+            // display on/off control
+            4 => {
+                self.display_on = cmd & 0b100 != 0;
+                self.cursor_on = cmd & 0b010 != 0;
+                self.blink_on = cmd & 0b001 != 0;
+            }
+            // cursor or display shift
+            3 => {
+                let right = cmd & 0b0100 != 0;
+                if cmd & 0b1000 != 0 {
+                    self.shift_display(!right);
+                } else {
+                    self.step(right);
+                }
+            }
+            // function set
+            2 => {
+                self.eight_bit = cmd & 0b10000 != 0;
+                self.two_lines = cmd & 0b01000 != 0;
+            }
+            // set CGRAM address
+            1 => {
+                self.ac = cmd & 0x3F;
+                self.in_cgram = true;
+            }
+            // set DDRAM address
+            _ => {
+                self.ac = cmd & 0x7F;
+                self.in_cgram = false;
+            }
+        }
+        self.dirty = true;
+    }
+
+    fn data(&mut self, byte: u8) {
+        if self.in_cgram {
+            self.cgram[self.ac as usize] = byte;
+        } else {
+            self.ddram[self.ac as usize & 0x7F] = byte;
+            if self.shift_on_write {
+                self.shift_display(self.increment);
+            }
+        }
+        self.step(self.increment);
+        self.dirty = true;
+    }
+
+    /// Take a byte from the data lines when E goes low
+    fn write(&mut self, rs: bool, bus: u8) {
+        let byte = if self.eight_bit {
+            if bus & 0xF0 == 0x20 {
+                // a function set that switches to 4 bit mode, when wired like that only D4-D7
+                // are connected and the low nibble is whatever the port floats at
+                bus & 0xF0
+            } else {
+                bus
+            }
+        } else {
+            // high nibble first, both on D4-D7
+            match self.nibble.take() {
+                None => {
+                    self.nibble = Some(bus >> 4);
+                    return
+                }
+                Some(high) => high << 4 | bus >> 4
+            }
+        };
+        if rs {
+            self.data(byte);
+        } else {
+            self.command(byte);
+        }
+    }
+
+    /// What goes on the data lines when E goes high for a read
+    fn read(&mut self, rs: bool) -> u8 {
+        // never busy, so bit 7 is always 0
+        let value = if rs {
+            if self.in_cgram {self.cgram[self.ac as usize]} else {self.ddram[self.ac as usize & 0x7F]}
+        } else {
+            self.ac
+        };
+        if !self.eight_bit {
+            // unused lines float high
+            self.read_low = !self.read_low;
+            if self.read_low {
+                return value | 0x0F
+            }
+        }
+        if rs {
+            self.step(self.increment);
+        }
+        if self.eight_bit {value} else {value << 4 | 0x0F}
+    }
+
+    /// DDRAM address of a character on the display
+    fn addr(&self, line: u8, column: u8) -> usize {
+        (line * 0x40 + (column + self.shift) % LCD_LINE_LEN) as usize
+    }
+
+    /// Characters as they appear on the display, blank if its off
+    fn line(&self, line: u8) -> Vec<u8> {
+        (0..LCD_COLUMNS).map(|c| {
+            if !self.display_on || (line == 1 && !self.two_lines) {0x20} else {self.ddram[self.addr(line, c)]}
+        }).collect()
+    }
+
+    /// Whether the cursor sits on this character
+    fn cursor_at(&self, line: u8, column: u8) -> bool {
+        self.display_on && !self.in_cgram && (self.cursor_on || self.blink_on) &&
+            self.addr(line, column) == self.ac as usize
+    }
+
+    /// Redraw in the terminal every now and then if something changed
+    pub fn tick(&mut self, cycles: u64) {
+        if self.mode != LcdMode::Terminal || !self.dirty {
+            return
+        }
+        self.idle += cycles;
+        if self.idle < LCD_POLL_CYCLES {
+            return
+        }
+        self.idle = 0;
+        if self.last_draw.elapsed() >= LCD_REDRAW {
+            self.draw_terminal();
+        }
+    }
+
+    /**
+    Draw the LCD with a frame around it in the top right of the terminal
+
+    Goes to stderr so it still shows up when stdout is piped somewhere,
+    the cursor gets put back where it was
+    */
+    fn draw_terminal(&mut self) {
+        self.dirty = false;
+        self.last_draw = Instant::now();
+        let width = crossterm::terminal::size().map(|(w, _)| w).unwrap_or(80);
+        let col = width.saturating_sub(LCD_COLUMNS as u16 + 2).max(1);
+        let mut out = String::from("\x1b7");
+        let bar = "─".repeat(LCD_COLUMNS as usize);
+        out += &format!("\x1b[1;{}H┌{}┐", col, bar);
+        for line in 0..2 {
+            out += &format!("\x1b[{};{}H│", line + 2, col);
+            for (c, code) in self.line(line).into_iter().enumerate() {
+                let ch = glyph(code);
+                if self.cursor_at(line, c as u8) {
+                    // underline for the cursor, blinking inverse for the block
+                    let attr = if self.blink_on {"\x1b[5;7m"} else {"\x1b[4m"};
+                    out += &format!("{}{}\x1b[0m", attr, ch);
+                } else {
+                    out.push(ch);
+                }
+            }
+            out.push('│');
+        }
+        out += &format!("\x1b[4;{}H└{}┘\x1b8", col, bar);
+        let mut err = io::stderr();
+        let _ = err.write_all(out.as_bytes());
+        let _ = err.flush();
+    }
+}
+
+/// Closest thing to a character of the A00 character ROM
+fn glyph(code: u8) -> char {
+    match code {
+        // custom characters, the terminal cant show those
+        0x00..=0x0F => '▒',
+        0x5C => '¥',
+        0x7E => '→',
+        0x7F => '←',
+        0x20..=0x7D => code as char,
+        0xDF => '°',
+        0xFF => '█',
+        _ => ' '
+    }
+}
+
+impl Bus {
+    /// Follow the VIA ports after they were written, the LCD acts on edges of E
+    pub fn update_lcd(&mut self) {
+        if self.lcd.mode == LcdMode::Off {
+            return
+        }
+        let control = self.via.port_a();
+        let e = control & LcdPin::E as u8 != 0;
+        if e == self.lcd.last_e {
+            return
+        }
+        self.lcd.last_e = e;
+        let rs = control & LcdPin::Rs as u8 != 0;
+        let rw = control & LcdPin::Rw as u8 != 0;
+        if e && rw {
+            // drive the data lines until E goes low again
+            self.via.pins_b = self.lcd.read(rs);
+        } else if !e {
+            if rw {
+                self.via.pins_b = 0xFF;
+            } else {
+                self.lcd.write(rs, self.via.port_b());
+            }
+        }
+    }
+
+    /// Draw the LCD into the top left of the window, on top of everything
+    pub fn draw_lcd(&mut self) {
+        if self.lcd.mode != LcdMode::Window {
+            return
+        }
+        let lcd = &self.lcd;
+        let scale = self.gpu_scale as i32;
+        let back = raylib::ffi::Color {r: 0x9A, g: 0xC8, b: 0x3C, a: 0xFF};
+        let ink = raylib::ffi::Color {r: 0x20, g: 0x30, b: 0x10, a: 0xFF};
+        let cell_w = LCD_CHAR_W + 1;
+        let cell_h = LCD_CHAR_H + 1;
+        // the blinking block is on for about half a second
+        let blink = unsafe { raylib::ffi::GetTime() as f32 % 1.0 < 0.5 };
+        unsafe {
+            raylib::ffi::DrawRectangle(0, 0, (LCD_COLUMNS as i32 * cell_w + 3) * scale,
+                (2 * cell_h + 3) * scale, back);
+        }
+        for line in 0..2 {
+            for (c, code) in lcd.line(line).into_iter().enumerate() {
+                let x = (2 + c as i32 * cell_w) * scale;
+                let y = (2 + line as i32 * cell_h) * scale;
+                if code < 0x10 {
+                    // custom character from CGRAM, 5 pixels per row
+                    for row in 0..LCD_CHAR_H {
+                        let bits = lcd.cgram[(code as usize & 0x07) * 8 + row as usize];
+                        for px in 0..LCD_CHAR_W {
+                            if bits & (0x10 >> px) != 0 {
+                                unsafe {
+                                    raylib::ffi::DrawRectangle(x + px * scale, y + row * scale, scale, scale, ink);
+                                }
+                            }
+                        }
+                    }
+                } else {
+                    let mut utf8 = [0; 4];
+                    let mut text: Vec<i8> = glyph(code).encode_utf8(&mut utf8).bytes().map(|b| b as i8).collect();
+                    text.push(0);
+                    unsafe {
+                        raylib::ffi::DrawText(text.as_ptr(), x, y, LCD_CHAR_H * scale, ink);
+                    }
+                }
+                if lcd.cursor_at(line, c as u8) {
+                    unsafe {
+                        if lcd.blink_on && blink {
+                            raylib::ffi::DrawRectangle(x, y, LCD_CHAR_W * scale, LCD_CHAR_H * scale, ink);
+                        } else if lcd.cursor_on {
+                            raylib::ffi::DrawRectangle(x, y + (LCD_CHAR_H - 1) * scale, LCD_CHAR_W * scale, scale, ink);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
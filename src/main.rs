@@ -1,14 +1,52 @@
 #![allow(dead_code)]
 
 use std::{env::args, fs, process};
+use std::io::{self, IsTerminal};
 use crossterm::{event::{self, Event, KeyCode, KeyModifiers}, terminal::{disable_raw_mode, enable_raw_mode}};
 use std::time::Duration;
 use raylib;
-use std::io::{self, Write};
 
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
 
+mod sprites;
+mod palette;
+mod gpu;
+mod keyboard;
+mod gamepad;
+mod sound;
+mod vsync;
+mod window;
+mod textures;
+mod terminal;
+mod input;
+mod acia;
+mod serial;
+mod via;
+mod lcd;
+mod rtc;
+mod rng;
+mod files;
+mod disk;
+mod dma;
+
+use gpu::{GpuStatus, GPU_RUN_LOC};
+use keyboard::Keyboard;
+use sound::Sound;
+use vsync::VSync;
+use window::Window;
+use terminal::Terminal;
+use input::Input;
+use acia::Acia;
+use serial::Serial;
+use via::Via;
+use lcd::{Lcd, LcdMode};
+use rtc::Rtc;
+use rng::Rng;
+use files::Files;
+use disk::Disk;
+use dma::Dma;
+
 // TODO: enum?
 /// 8 Kibibytes
 static K8: usize = 0x2000;
@@ -28,6 +66,10 @@ static STACK_BASE: usize = 0x100;
 static RV_LOC_LOW: usize = 0xfffc;
 /// Reset Vector high byte
 static RV_LOC_HIGH: usize = 0xfffd;
+/// IRQ Vector low byte
+static IRQ_LOC_LOW: usize = 0xfffe;
+/// IRQ Vector high byte
+static IRQ_LOC_HIGH: usize = 0xffff;
 /// GPU Flag bytes
 static GPU_LOC: usize = 0xfff0;
 /// Window Width low byte
@@ -69,11 +111,17 @@ struct Emulator {
     /// Whether the GPU is enabled
     graphical: bool,
     /// Scale used on all values by raylib functions
-    gpu_scale: u8
+    gpu_scale: u8,
+    /// Cycles executed since reset
+    cycles: u64,
+    /// Where key presses come from
+    input: Input,
+    /// Stop once piped input is used up and the ROM wants more
+    exit_on_eof: bool,
 
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 /// Address Bus which handles all reads and writes, including IO
 struct Bus {
     /// RAM: Zeropage, Stack, general purpose RAM
@@ -88,13 +136,42 @@ struct Bus {
     gpu_enable: bool,
     /// All raylib calls are scaled, so you can use a larger 
     /// window than 256x256 since thats tiny on modern systems
-    // TODO: initialise to 1 instead of 0 if the ROM doesnt supply it
-    gpu_scale: u8
+    gpu_scale: u8,
+    /// Address of the next argument to read for the running GPU command
+    gpu_arg: u16,
+    /// Key event and character queues of the raylib window
+    keyboard: Keyboard,
+    /// Sound chip
+    sound: Sound,
+    /// Frame counter and vblank flag
+    vsync: VSync,
+    /// Window options from the ROM header
+    window: Window,
+    /// Textures defined with DefineTexture
+    textures: Vec<Option<raylib::ffi::Texture2D>>,
+    /// Output device at $4000
+    terminal: Terminal,
+    /// 6551 serial port at $5000
+    acia: Acia,
+    /// 6522 timers and ports at $4020
+    via: Via,
+    /// Character LCD on the VIA ports
+    lcd: Lcd,
+    /// Clock and counters at $4030
+    rtc: Rtc,
+    /// Random numbers at $4040
+    rng: Rng,
+    /// Host files at $4050
+    files: Files,
+    /// Disk image at $4060
+    disk: Disk,
+    /// Memory copies at $4070
+    dma: Dma
 }
 
 #[allow(non_camel_case_types)]
 #[repr(u8)]
-#[derive(Debug, FromPrimitive)]
+#[derive(Debug, FromPrimitive, Clone, Copy)]
 /// An enum of all possible (legal) Opcodes
 // TODO: descriptions here?
 enum Instruction {
@@ -262,6 +339,28 @@ enum Instruction {
     INC_ABS_X   = 0xFE
 }
 
+/// Cycles each opcode takes, indexed by opcode
+/// page crossings and taken branches arent counted
+static CYCLES: [u8; 256] = [
+//  0  1  2  3  4  5  6  7  8  9  A  B  C  D  E  F
+    7, 6, 2, 8, 3, 3, 5, 5, 3, 2, 2, 2, 4, 4, 6, 6, // 0
+    2, 5, 2, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // 1
+    6, 6, 2, 8, 3, 3, 5, 5, 4, 2, 2, 2, 4, 4, 6, 6, // 2
+    2, 5, 2, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // 3
+    6, 6, 2, 8, 3, 3, 5, 5, 3, 2, 2, 2, 3, 4, 6, 6, // 4
+    2, 5, 2, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // 5
+    6, 6, 2, 8, 3, 3, 5, 5, 4, 2, 2, 2, 5, 4, 6, 6, // 6
+    2, 5, 2, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // 7
+    2, 6, 2, 6, 3, 3, 3, 3, 2, 2, 2, 2, 4, 4, 4, 4, // 8
+    2, 6, 2, 6, 4, 4, 4, 4, 2, 5, 2, 5, 5, 5, 5, 5, // 9
+    2, 6, 2, 6, 3, 3, 3, 3, 2, 2, 2, 2, 4, 4, 4, 4, // A
+    2, 5, 2, 5, 4, 4, 4, 4, 2, 4, 2, 4, 4, 4, 4, 4, // B
+    2, 6, 2, 8, 3, 3, 5, 5, 2, 2, 2, 2, 4, 4, 6, 6, // C
+    2, 5, 2, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // D
+    2, 6, 2, 8, 3, 3, 5, 5, 2, 2, 2, 2, 4, 4, 6, 6, // E
+    2, 5, 2, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // F
+];
+
 #[repr(u8)]
 /// Masks to work with single bits of the SR
 // TODO: bitflags crate?
@@ -306,7 +405,7 @@ impl From<u8> for EErr {
 impl Bus {
     /// initialise the bus, cloning the ROM into the correct section
     pub fn init(rom: [u8; K32]) -> Bus {
-        Bus {
+        let mut bus = Bus {
             // memory sections are initialised to 0
             // not technically accurate but whatever
             ram: [0; K16],
@@ -317,8 +416,30 @@ impl Bus {
             rom: rom.clone(),
             // disable gpu by default
             gpu_enable: false,
-            gpu_scale: 1
-        }
+            gpu_scale: 1,
+            gpu_arg: 0x6001,
+            keyboard: Keyboard::init(),
+            sound: Sound::init(),
+            vsync: VSync::init(),
+            window: Window::init(),
+            textures: vec![None; textures::TEXTURE_SLOTS],
+            terminal: Terminal::init(),
+            acia: Acia::init(),
+            via: Via::init(),
+            lcd: Lcd::init(),
+            rtc: Rtc::init(),
+            rng: Rng::init(),
+            files: Files::init(),
+            disk: Disk::init(),
+            dma: Dma::init()
+        };
+        // give the palette some usable colours
+        bus.load_default_palette();
+        // version and capabilities for the ROM to check
+        bus.init_gpu_registers();
+        // keys to use for the controller if theres no gamepad
+        bus.load_default_keymap();
+        bus
     }
 
     /**
@@ -336,18 +457,41 @@ impl Bus {
             }
             // io
             0x4000..=0x5FFF => {
-                // address for reading a key and status 
-                // TODO: refactor addresses to constants
-                if addr == 0x4001 {
-                    // reset status
-                    // TODO: refactor address to constants
-                    self.write(0x4002, 0);
-                    // offset since theyre seperate "modules",
-                    // not one continuous address space
-                    return self.io[addr as usize - 0x4000]
-                } else if addr == 0x4002 {
-                    // return the status
-                    return self.io[addr as usize - 0x4000]
+                // key buffer
+                if matches!(addr, 0x4001..=0x4006) {
+                    return self.terminal.read(addr)
+                }
+                // timers and ports
+                if matches!(addr, 0x4020..=0x402F) && self.via.enabled {
+                    return self.via.read(addr)
+                }
+                // clock
+                if matches!(addr, 0x4030..=0x403F) {
+                    return self.rtc.read(addr)
+                }
+                // random numbers
+                if addr == 0x4040 {
+                    return self.rng.next()
+                }
+                // host files
+                if matches!(addr, 0x4050..=0x405F) && self.files.enabled() {
+                    return self.read_files(addr)
+                }
+                // disk
+                if matches!(addr, 0x4060..=0x406F) && self.disk.enabled() {
+                    return self.read_disk(addr)
+                }
+                // dma
+                if matches!(addr, 0x4070..=0x407F) {
+                    return self.read_dma(addr)
+                }
+                // sound chip
+                if matches!(addr, 0x4100..=0x410F) {
+                    return self.sound.read(addr)
+                }
+                // serial port
+                if matches!(addr, 0x5000..=0x5003) && self.acia.enabled {
+                    return self.read_acia(addr)
                 }
                 0
             }
@@ -357,10 +501,34 @@ impl Bus {
                 if matches!(addr, 0x6001..=0x60FF) {
                     return self.gpu[addr as usize - 0x6000]
                 }
-                // requested key
-                if addr == 0x6100 {
+                // requested key, gpu mode, version, capabilities and status
+                if matches!(addr, 0x6100..=0x6106) {
+                    return self.gpu[addr as usize - 0x6000]
+                }
+                // frame counter and vblank
+                if matches!(addr, 0x6108..=0x610D) {
+                    return self.read_vsync(addr)
+                }
+                // mouse position, buttons and wheel
+                if matches!(addr, 0x6110..=0x6115) {
+                    return self.read_mouse(addr)
+                }
+                // key event and character queues
+                if matches!(addr, 0x6120..=0x6126) {
+                    return self.read_keyboard(addr)
+                }
+                // controller
+                if matches!(addr, 0x6130..=0x6132) {
+                    return self.read_gamepad(addr)
+                }
+                // controller key map, sprite attribute table, palette and pattern memory
+                if matches!(addr, 0x6140..=0x6157 | 0x6200..=0x62FF | 0x6400..=0x66FF | 0x7800..=0x7FFF) {
                     return self.gpu[addr as usize - 0x6000]
                 }
+                // sprite collision status
+                if matches!(addr, 0x6300..=0x6302) {
+                    return self.read_sprite_status(addr)
+                }
                 // otherwise just 0 for now
                 0x00
             }
@@ -376,7 +544,6 @@ impl Bus {
     which will redirect it to RAM or the reserved spaces (IO)
     */
     pub fn write(&mut self, addr: u16, byte: u8) {
-        // TODO: refactor addresses to enum? constants?
         match addr {
             // RAM
             0x0000..=0x3FFF => {
@@ -385,120 +552,50 @@ impl Bus {
             // io
             0x4000..=0x5FFF => {
                 if addr == 0x4000 {
-                    // ignore backspaces
-                    if byte == 8 {
-                        return
-                    }
-                    print!("{}", byte as char);
-                    // check if its a newline to add a CR
-                    // we need the CR since we're in raw mode
-                    if byte == 10 {
-                        print!("\r");
-                    }
-                    io::stdout().flush().unwrap();
+                    // handles control characters and escape sequences
+                    self.terminal.write(byte);
+                }
+                // key buffer
+                if matches!(addr, 0x4001..=0x4006) {
+                    self.terminal.write_input(addr, byte);
+                }
+                // timers and ports
+                if matches!(addr, 0x4020..=0x402F) && self.via.enabled {
+                    self.via.write(addr, byte);
+                    self.update_lcd();
+                }
+                // clock
+                if matches!(addr, 0x4030..=0x403F) {
+                    self.rtc.write(addr);
+                }
+                // host files
+                if matches!(addr, 0x4050..=0x405F) && self.files.enabled() {
+                    self.write_files(addr, byte);
+                }
+                // disk
+                if matches!(addr, 0x4060..=0x406F) && self.disk.enabled() {
+                    self.write_disk(addr, byte);
+                }
+                // dma
+                if matches!(addr, 0x4070..=0x407F) {
+                    self.write_dma(addr, byte);
                 }
-                // so the fuckin input loop can write to these lmao
-                if (addr == 0x4001) || (addr == 0x4002) {
-                    self.io[addr as usize - 0x4000] = byte;
+                // sound chip
+                if matches!(addr, 0x4100..=0x410F) {
+                    self.sound.write(addr, byte);
+                }
+                // serial port
+                if matches!(addr, 0x5000..=0x5003) && self.acia.enabled {
+                    self.write_acia(addr, byte);
                 }
             }
             // gpu
             0x6000..=0x7FFF => {
-                // only do this if gpu is enabled
-                if self.gpu_enable {
-                    // 0x6000 is like the "Enable Pin" of the "GPU"
-                    // TODO: refactor address to constant
-                    if addr == 0x6000 {
-                        // TODO: refactor to enum, no more magical values pls
-                        match byte {
-                            // BeginDrawing
-                            0xBD => {
-                                // tell raylib to start a new frame?
-                                // TODO: look up what this does
-                                unsafe {
-                                    raylib::ffi::BeginDrawing();
-                                }
-                            }
-                            // EndDrawing
-                            0xED => {
-                                // tell raylib to handle 
-                                // drawing the frame and waiting 
-                                // for the next frame
-                                unsafe {
-                                    raylib::ffi::EndDrawing();
-                                }
-                            }
-                            // ClearBackground
-                            0xCB => {
-                                // get colour components
-                                let r = self.read(0x6001); 
-                                let g = self.read(0x6002);
-                                let b = self.read(0x6003);
-                                // make colour
-                                let col = raylib::ffi::Color {r, g, b, a: 0xFF};
-                                unsafe {
-                                    // run command
-                                    raylib::ffi::ClearBackground(col);
-                                }
-                            }
-                            // DrawRectangle
-                            0xD5 => {
-                                // read rectangle position and size
-                                let x = self.read(0x6001); 
-                                let y = self.read(0x6002);
-                                let w = self.read(0x6003);
-                                let h = self.read(0x6004);
-                                // get colour components
-                                let r = self.read(0x6005); 
-                                let g = self.read(0x6006);
-                                let b = self.read(0x6007);
-                                // make colour
-                                let col = raylib::ffi::Color {r, g, b, a: 0xFF};
-                                unsafe {
-                                    // run command, scaled 
-                                    raylib::ffi::DrawRectangle(x as i32 * self.gpu_scale as i32, 
-                                        y as i32 * self.gpu_scale as i32, 
-                                        w as i32 * self.gpu_scale as i32, 
-                                        h as i32 * self.gpu_scale as i32, 
-                                        col);
-                                }
-                            }
-                            // IsKeyDown
-                            0xDE => {
-                                // get the key the program wants to know
-                                let key = self.read(0x6001);
-                                unsafe {
-                                    // is the key requested down?
-                                    let is_down = raylib::ffi::IsKeyDown(key as i32);
-                                    // set whether key is down or not
-                                    self.gpu[0x100] = if is_down {0x01} else {0x0};
-                                }
-                            }
-                            // DrawLine
-                            0xD1 => {
-                                // p1 
-                                let startx = self.read(0x6001);
-                                let starty = self.read(0x6002);
-                                // p2
-                                let endx   = self.read(0x6003);
-                                let endy   = self.read(0x6004);
-                                // get colour components
-                                let r = self.read(0x6005); 
-                                let g = self.read(0x6006);
-                                let b = self.read(0x6007);
-                                // make colour
-                                let col = raylib::ffi::Color {r, g, b, a: 0xFF};
-                                unsafe {
-                                    // also scaled 
-                                    raylib::ffi::DrawLine(startx as i32 * self.gpu_scale as i32,
-                                        starty as i32 * self.gpu_scale as i32,
-                                        endx as i32 * self.gpu_scale as i32,
-                                        endy as i32 * self.gpu_scale as i32,
-                                        col);
-                                }
-                            }
-                            _ => {}
-                        }
+                // 0x6000 is like the "Enable Pin" of the "GPU"
+                if addr as usize == GPU_RUN_LOC {
+                    // only do this if gpu is enabled
+                    if self.gpu_enable {
+                        self.run_gpu_command(byte);
                         // clear arguments after a call
                         for i in 0x6001..=0x60FF {
                             // put 0 there so the cpu can 
@@ -506,8 +603,10 @@ impl Bus {
                             // character to get
                             self.write(i, 0x00);
                         }
-                    }   
-
+                    } else {
+                        // theres no window to draw to
+                        self.set_gpu_status(GpuStatus::Disabled);
+                    }
                 }
                 // 0x6001 - 0x60FF -> Arguments to GPU calls
                 if matches!(addr, 0x6001..=0x60FF) {
@@ -516,6 +615,18 @@ impl Bus {
                     // arguments to the "GPU" (raylib)
                     self.gpu[addr as usize - 0x6000] = byte;
                 }
+                // vblank control
+                if matches!(addr, 0x6108..=0x610D) {
+                    self.write_vsync(addr, byte);
+                }
+                // keyboard IRQ enable
+                if matches!(addr, 0x6120..=0x6126) {
+                    self.write_keyboard(addr, byte);
+                }
+                // gpu mode, controller key map, sprite attribute table, palette and pattern memory
+                if matches!(addr, 0x6101 | 0x6140..=0x6157 | 0x6200..=0x62FF | 0x6400..=0x66FF | 0x7800..=0x7FFF) {
+                    self.gpu[addr as usize - 0x6000] = byte;
+                }
             }
             // ROM 
             0x8000..=0xFFFF => {
@@ -525,6 +636,21 @@ impl Bus {
             }
         }
     }
+
+    /// Whether any device wants an interrupt
+    pub fn irq(&self) -> bool {
+        self.keyboard.irq() || self.vsync.irq() || self.acia.irq() || self.via.irq()
+    }
+
+    /// Let the devices know how many cycles the last instruction took
+    pub fn tick(&mut self, cycles: u64) {
+        self.sound.tick(cycles);
+        self.vsync.tick(cycles);
+        self.via.tick(cycles);
+        self.lcd.tick(cycles);
+        self.rtc.tick(cycles);
+        self.tick_acia();
+    }
 }
 
 
@@ -571,10 +697,29 @@ impl Emulator {
             sr: SRMask::Reserved as u8, // bit 5 is always set when pushing so set it
             debug: debug, // to make debug optional
             graphical: use_graphical, // whether to use raylib
-            gpu_scale: 1 // scale if gpu is used
+            gpu_scale: 1, // scale if gpu is used
+            cycles: 0,
+            input: Input::Terminal,
+            exit_on_eof: false,
         }
     }
 
+    /**
+    Push PC and SR, disable further interrupts and jump to the 
+    address stored at vector
+    */
+    fn interrupt(&mut self, vector: u16) {
+        // unlike JSR the address pushed is the next instruction
+        self.push_to_stack((self.pc >> 8) as u8);
+        self.push_to_stack((self.pc & 0xff) as u8);
+        // Break is only set when BRK or PHP push SR
+        self.push_to_stack(self.sr & !(SRMask::Break as u8) | SRMask::Reserved as u8);
+        self.set_sr_bit(SRMask::Interrupt, true);
+        self.pc = self.read_word_at(vector);
+        // same as BRK
+        self.cycles += 7;
+    }
+
     /// Print the current state of the CPU
     fn print_state(&mut self) {
         println!("-----------");
@@ -1059,6 +1204,8 @@ impl Emulator {
         }
         // increment PC to the Operand or next instruction
         self.pc = self.pc.wrapping_add(1);
+        // count the cycles the instruction takes
+        self.cycles += CYCLES[inst as usize] as u64;
 
         // TODO: turn into just base instruction
         //       move addressing mode to respective handler 
@@ -2297,10 +2444,16 @@ impl Emulator {
             // get the position of the title in the ROM from the ROM
             let title_ptr = self.read_word_at(W_T_LOC as u16);
             // get window scale
-            let gpu_scale = self.read_byte_at(W_S_LOC as u16);
+            let mut gpu_scale = self.read_byte_at(W_S_LOC as u16);
+            // 0 would make everything invisible
+            if gpu_scale == 0 {
+                gpu_scale = 1;
+            }
             self.bus.gpu_scale = gpu_scale;
             // get FPS
             let fps = self.read_byte_at(W_FPS_LOC as u16);
+            // vblank timer needs it too
+            self.bus.vsync.fps = fps;
             // vec to store raw title bytes
             let mut title_vec: Vec<i8> = vec![];
             // start at title_ptr
@@ -2312,16 +2465,23 @@ impl Emulator {
             }
             title_vec.push(0); // push null for terminator for raylib
 
+            // resizable, fullscreen, scaling and background colour
+            self.bus.read_window_header();
+
             unsafe {
                 // make raylib shut up 
                 raylib::ffi::SetTraceLogLevel(7);
-                // times the window scale!
-                raylib::ffi::InitWindow(width as i32 * gpu_scale as i32, 
-                    height as i32 * gpu_scale as i32, 
-                    title_vec.as_ptr());
+                raylib::ffi::SetConfigFlags(self.bus.window.config_flags());
+                // times the window scale, and wider or higher if the pixels arent square
+                let (win_w, win_h) = self.bus.window.window_size(width as i32 * gpu_scale as i32, 
+                    height as i32 * gpu_scale as i32);
+                raylib::ffi::InitWindow(win_w, win_h, title_vec.as_ptr());
                 // set fps
                 raylib::ffi::SetTargetFPS(fps as i32);
             }
+            // the ROM draws at this size, even if the window changes
+            self.bus.window.open(width as i32 * gpu_scale as i32, 
+                height as i32 * gpu_scale as i32);
             
 
         }
@@ -2370,7 +2530,7 @@ impl Emulator {
             }
 
             // check if a key has been pressed and update memory if its the case
-            if event::poll(Duration::from_millis(0)).unwrap() {
+            if matches!(self.input, Input::Terminal) && event::poll(Duration::from_millis(0)).unwrap() {
                 // TODO: actually understand ts
                 if let Event::Key(key_event) = event::read().unwrap() {
                     // make ctrl+c work in raw mode
@@ -2378,34 +2538,29 @@ impl Emulator {
                         key_event.code == KeyCode::Char('c') {
                             break 'end
                     }
-                    // set the status to true
-                    // TODO: refactor addresses to constants
-                    self.bus.write(0x4002, 0x01);
-                    match key_event.code {
-                        KeyCode::Char(c) => {
-                            // give the byte to the cpu at $4001
-                            self.bus.write(0x4001, c as u8);  
-                        }
-                        KeyCode::Enter => {
-                            // TODO: refactor to constant
-                            self.bus.write(0x4001, 10);  
-                        }
-                        KeyCode::Backspace => {
-                            // TODO: refactor to constant
-                            self.bus.write(0x4001, 8); 
-                        }
-                        KeyCode::Esc => {
-                            // TODO: refactor to constant
-                            self.bus.write(0x4001, 27);
-                        }
-                        _ => {}
-                    }
-                    
+                    // queue it up for the cpu at $4001
+                    self.bus.terminal.push_keys(&input::key_bytes(key_event));
                 }
             }
+            // piped input, only as much as fits so nothing gets dropped
+            while !self.bus.terminal.input_full() && let Some(byte) = self.input.next() {
+                self.bus.terminal.push_key(byte);
+            }
+            // nothing else is coming, so waiting for it would never end
+            if self.exit_on_eof && self.input.ended() && self.bus.terminal.starved() {
+                break 'end
+            }
+
+            // to tell the devices how long this step took
+            let start = self.cycles;
+
+            // service interrupts unless theyre disabled
+            if self.bus.irq() && !self.get_psr_bit(SRMask::Interrupt) {
+                self.interrupt(IRQ_LOC_LOW as u16);
+                trace!(self, "IRQ -> ${:04X}", self.pc);
+            }
 
             // run an instruction and check for errors
-            // TODO: handle interrupts (somehow)
             if let Some(e) = self.exec_instruction().err() {
                 match e {
                     // not a real instruction
@@ -2419,6 +2574,10 @@ impl Emulator {
                     }
                 }
             }
+            // the cpu waits while the dma controller has the bus
+            self.cycles += self.bus.dma.take_stall();
+            // let the devices catch up with the cpu
+            self.bus.tick(self.cycles - start);
         }
 
         if self.graphical {
@@ -2452,7 +2611,56 @@ fn load_rom(path: &str, rom: &mut [u8; K32]) -> Result<(), String> {
 fn main() {
     let mut a = args().into_iter();
     let _program_name = a.next(); // ignored
-    let rom_name = a.next().expect("You need to supply a 32KiB ROM");
+    let mut rom_name = None;
+    // write the sound to this file instead of playing it
+    let mut wav_path = None;
+    // dont handle control characters in the output
+    let mut raw_output = false;
+    // read input from this file instead of the keyboard
+    let mut input_path = None;
+    // size of the key buffer
+    let mut input_depth = None;
+    // stop when the input runs out
+    let mut exit_on_eof = false;
+    // 6551 serial port at $5000
+    let mut acia = false;
+    // 6522 VIA at $4020
+    let mut via = false;
+    // HD44780 LCD on the VIA
+    let mut lcd = LcdMode::Off;
+    // same random numbers every run
+    let mut seed = None;
+    // directory ROMs can use files in
+    let mut files_dir = None;
+    // disk image for the block device
+    let mut disk_image = None;
+    // connect the terminal to a socket or pty instead
+    let mut serial = None;
+    while let Some(arg) = a.next() {
+        match arg.as_str() {
+            "--wav" => wav_path = Some(a.next().expect("--wav needs a file name")),
+            "--raw-output" => raw_output = true,
+            "--acia" => acia = true,
+            "--via" => via = true,
+            "--disk" => disk_image = Some(a.next().expect("--disk needs an image file")),
+            "--files" => files_dir = Some(a.next().expect("--files needs a directory")),
+            "--seed" => seed = Some(a.next().and_then(|n| n.parse().ok())
+                .expect("--seed needs a number")),
+            "--lcd" => lcd = a.next().and_then(|m| match m.as_str() {
+                "terminal" => Some(LcdMode::Terminal),
+                "window" => Some(LcdMode::Window),
+                _ => None
+            }).expect("--lcd needs terminal or window"),
+            "--serial" => serial = Some(a.next().as_deref().and_then(Serial::parse)
+                .expect("--serial needs tcp:<port> or pty")),
+            "--input-depth" => input_depth = Some(a.next().and_then(|n| n.parse().ok())
+                .expect("--input-depth needs a number from 1 to 255")),
+            "--exit-on-eof" => exit_on_eof = true,
+            "--input" => input_path = Some(a.next().expect("--input needs a file name")),
+            _ => rom_name = Some(arg)
+        }
+    }
+    let rom_name = rom_name.expect("You need to supply a 32KiB ROM");
     // temporary buffer to be copied later
     let mut code: [u8; K32] = [0x00; K32];
     
@@ -2466,12 +2674,81 @@ fn main() {
     // init and run emulator
     // TODO: debug flag
     let mut e = Emulator::init(code, false);
+    if let Some(path) = wav_path {
+        e.bus.sound.headless(&path).unwrap_or_else(|e| {
+            eprintln!("[ERROR]: IO Error: {}", e);
+            process::exit(1);
+        });
+    }
+    if raw_output {
+        e.bus.terminal.set_raw(true);
+    }
+    if let Some(depth) = input_depth {
+        e.bus.terminal.set_input_depth(depth);
+    }
+    e.bus.acia.enabled = acia;
+    // the window LCD gets drawn by EndDrawing, without the GPU there is no window
+    if lcd == LcdMode::Window && !e.graphical {
+        eprintln!("[ERROR]: --lcd window needs a ROM that turns on the GPU, use --lcd terminal instead");
+        process::exit(1);
+    }
+    // the LCD hangs off the VIA
+    e.bus.via.enabled = via || lcd != LcdMode::Off;
+    e.bus.lcd.mode = lcd;
+    if let Some(seed) = seed {
+        e.bus.rng = Rng::seeded(seed);
+    }
+    if let Some(path) = disk_image {
+        e.bus.disk.attach(&path).unwrap_or_else(|e| {
+            eprintln!("[ERROR]: IO Error: {}", e);
+            process::exit(1);
+        });
+    }
+    if let Some(dir) = files_dir {
+        e.bus.files.set_root(&dir).unwrap_or_else(|e| {
+            eprintln!("[ERROR]: IO Error: {}", e);
+            process::exit(1);
+        });
+    }
 
-    // enable raw mode to allow reading raw characters
-    enable_raw_mode().unwrap();
+    e.exit_on_eof = exit_on_eof;
+    // keys come from the terminal, unless stdin is piped or theres an input file
+    e.input = if let Some(serial) = serial {
+        let (input, output) = serial.open().unwrap_or_else(|e| {
+            eprintln!("[ERROR]: IO Error: {}", e);
+            process::exit(1);
+        });
+        e.bus.terminal.set_output(output);
+        input
+    } else {
+        match input_path {
+            Some(path) => {
+                let file = fs::File::open(&path).unwrap_or_else(|e| {
+                    eprintln!("[ERROR]: IO Error: {}", e);
+                    process::exit(1);
+                });
+                Input::stream(file)
+            }
+            None if io::stdin().is_terminal() => Input::Terminal,
+            None => Input::stream(io::stdin())
+        }
+    };
+    // raw mode only makes sense with a terminal
+    let raw_mode = matches!(e.input, Input::Terminal);
+
+    if raw_mode {
+        // enable raw mode to allow reading raw characters
+        enable_raw_mode().unwrap();
+    }
     e.run();
     // clean up
-    disable_raw_mode().unwrap();
+    if raw_mode {
+        disable_raw_mode().unwrap();
+    }
+    e.bus.sound.finish().unwrap_or_else(|e| {
+        eprintln!("[ERROR]: {}", e);
+        process::exit(1);
+    });
 }
 
 // TODO:    maybe add WDC extensions as an option? (are conditional enum fields a thing)
@@ -0,0 +1,48 @@
+use crate::Bus;
+
+/// Start of the palette, 256 entries of r, g, b
+pub static PALETTE_LOC: usize = 0x6400;
+/// Amount of palette entries
+pub static PALETTE_SIZE: usize = 256;
+
+/// What the first 16 entries get initialised to (CGA colours),
+/// the rest starts out black
+static DEFAULT_PALETTE: [[u8; 3]; 16] = [
+    [0x00, 0x00, 0x00], // black
+    [0x00, 0x00, 0xAA], // blue
+    [0x00, 0xAA, 0x00], // green
+    [0x00, 0xAA, 0xAA], // cyan
+    [0xAA, 0x00, 0x00], // red
+    [0xAA, 0x00, 0xAA], // magenta
+    [0xAA, 0x55, 0x00], // brown
+    [0xAA, 0xAA, 0xAA], // light grey
+    [0x55, 0x55, 0x55], // dark grey
+    [0x55, 0x55, 0xFF], // light blue
+    [0x55, 0xFF, 0x55], // light green
+    [0x55, 0xFF, 0xFF], // light cyan
+    [0xFF, 0x55, 0x55], // light red
+    [0xFF, 0x55, 0xFF], // light magenta
+    [0xFF, 0xFF, 0x55], // yellow
+    [0xFF, 0xFF, 0xFF], // white
+];
+
+impl Bus {
+    /// Put the default colours into the palette
+    pub fn load_default_palette(&mut self) {
+        for (i, col) in DEFAULT_PALETTE.iter().enumerate() {
+            let base = PALETTE_LOC - 0x6000 + i * 3;
+            self.gpu[base..base + 3].copy_from_slice(col);
+        }
+    }
+
+    /// Look up a colour in the palette
+    pub fn palette_colour(&self, index: u8) -> raylib::ffi::Color {
+        let base = PALETTE_LOC - 0x6000 + index as usize * 3;
+        raylib::ffi::Color {
+            r: self.gpu[base],
+            g: self.gpu[base + 1],
+            b: self.gpu[base + 2],
+            a: 0xFF
+        }
+    }
+}
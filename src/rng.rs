@@ -0,0 +1,38 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Reading gives a new random byte every time
+pub static RNG_LOC: u16 = 0x4040;
+
+#[derive(Clone)]
+/// xorshift64* generator, good enough for games and the same every run with --seed
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Seeded from the clock, so every run is different
+    pub fn init() -> Rng {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or(0);
+        Rng::seeded(nanos)
+    }
+
+    pub fn seeded(seed: u64) -> Rng {
+        // mix it up so small seeds dont start out with mostly zeros,
+        // and the state must never be 0
+        let mut z = seed.wrapping_add(0x9E3779B97F4A7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^= z >> 31;
+        Rng {
+            state: if z == 0 {1} else {z}
+        }
+    }
+
+    pub fn next(&mut self) -> u8 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        // the top bits are the best ones
+        (self.state.wrapping_mul(0x2545F4914F6CDD1D) >> 56) as u8
+    }
+}
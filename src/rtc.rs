@@ -0,0 +1,85 @@
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Start of the clock registers, writing here latches everything
+pub static RTC_LOC: usize = 0x4030;
+/// seconds, minutes, hours, day, month, years since 1900, weekday
+static RTC_TIME: usize = 0x4031;
+/// Milliseconds since the emulator started, 32 bit little endian
+static RTC_MILLIS: usize = 0x4038;
+/// CPU cycles since reset, low 32 bits little endian
+static RTC_CYCLES: usize = 0x403C;
+
+#[derive(Clone)]
+/// Wall clock and counters, read from a snapshot so the bytes all belong together
+pub struct Rtc {
+    start: Instant,
+    cycles: u64,
+    /// What the registers read as since the last latch
+    latched: [u8; 16],
+}
+
+impl Rtc {
+    pub fn init() -> Rtc {
+        let mut rtc = Rtc {
+            start: Instant::now(),
+            cycles: 0,
+            latched: [0; 16],
+        };
+        // so theres something sensible there before the first latch
+        rtc.latch();
+        rtc
+    }
+
+    /// Take a snapshot of the time and counters
+    fn latch(&mut self) {
+        let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        let days = (secs / 86400) as i64;
+        let (year, month, day) = civil_from_days(days);
+        let time = [
+            (secs % 60) as u8,
+            (secs / 60 % 60) as u8,
+            (secs / 3600 % 24) as u8,
+            day as u8,
+            month as u8,
+            (year - 1900) as u8,
+            // the 1st of january 1970 was a thursday
+            ((days + 4) % 7) as u8,
+        ];
+        let base = RTC_TIME - RTC_LOC;
+        self.latched[base..base + 7].copy_from_slice(&time);
+        let millis = self.start.elapsed().as_millis() as u32;
+        let base = RTC_MILLIS - RTC_LOC;
+        self.latched[base..base + 4].copy_from_slice(&millis.to_le_bytes());
+        let base = RTC_CYCLES - RTC_LOC;
+        self.latched[base..base + 4].copy_from_slice(&(self.cycles as u32).to_le_bytes());
+    }
+
+    pub fn read(&self, addr: u16) -> u8 {
+        self.latched[addr as usize - RTC_LOC]
+    }
+
+    /// Writing the first register latches, the rest are read only
+    pub fn write(&mut self, addr: u16) {
+        if addr as usize == RTC_LOC {
+            self.latch();
+        }
+    }
+
+    pub fn tick(&mut self, cycles: u64) {
+        self.cycles += cycles;
+    }
+}
+
+/// Days since 1970 to year, month and day, from Howard Hinnants date algorithms
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 {mp + 3} else {mp - 9};
+    let year = yoe + era * 400 + if month <= 2 {1} else {0};
+    (year, month, day)
+}
@@ -0,0 +1,86 @@
+use std::io::{self, Write};
+use std::net::TcpListener;
+
+use crate::input::Input;
+
+/// What the terminal device can be connected to instead of stdin and stdout
+pub enum Serial {
+    /// Wait for one connection on this port of localhost
+    Tcp(u16),
+    /// A pseudo terminal other programs can open like a serial port
+    Pty,
+}
+
+impl Serial {
+    /// Parse the argument of --serial, tcp:<port> or pty
+    pub fn parse(arg: &str) -> Option<Serial> {
+        if arg == "pty" {
+            return Some(Serial::Pty)
+        }
+        arg.strip_prefix("tcp:")?.parse().ok().map(Serial::Tcp)
+    }
+
+    /// Connect it, gives back where keys come from and where output goes
+    pub fn open(&self) -> io::Result<(Input, Box<dyn Write + Send>)> {
+        match self {
+            Serial::Tcp(port) => {
+                let listener = TcpListener::bind(("127.0.0.1", *port))?;
+                eprintln!("[INFO]: waiting for a connection on 127.0.0.1:{}", port);
+                let (stream, peer) = listener.accept()?;
+                eprintln!("[INFO]: {} connected", peer);
+                // small writes, we dont want them to wait around
+                stream.set_nodelay(true)?;
+                Ok((Input::stream(stream.try_clone()?), Box::new(stream)))
+            }
+            Serial::Pty => open_pty(),
+        }
+    }
+}
+
+#[cfg(unix)]
+/// Make a pseudo terminal in raw mode and print the path of its other end
+fn open_pty() -> io::Result<(Input, Box<dyn Write + Send>)> {
+    use std::ffi::CStr;
+    use std::fs::{File, OpenOptions};
+    use std::os::fd::{AsRawFd, FromRawFd};
+    use std::os::unix::fs::OpenOptionsExt;
+
+    unsafe {
+        let master = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY);
+        if master < 0 {
+            return Err(io::Error::last_os_error())
+        }
+        // owns the fd from here on so it gets closed on errors
+        let master = File::from_raw_fd(master);
+        let fd = master.as_raw_fd();
+        if libc::grantpt(fd) != 0 || libc::unlockpt(fd) != 0 {
+            return Err(io::Error::last_os_error())
+        }
+        let name = libc::ptsname(fd);
+        if name.is_null() {
+            return Err(io::Error::last_os_error())
+        }
+        let path = CStr::from_ptr(name).to_string_lossy().into_owned();
+        // bytes should go through exactly as they are, no echo or line editing
+        let mut termios = std::mem::zeroed();
+        if libc::tcgetattr(fd, &mut termios) != 0 {
+            return Err(io::Error::last_os_error())
+        }
+        libc::cfmakeraw(&mut termios);
+        if libc::tcsetattr(fd, libc::TCSANOW, &termios) != 0 {
+            return Err(io::Error::last_os_error())
+        }
+        // reading the master fails while nobody has the other end open,
+        // so keep it open ourselves for as long as the emulator runs
+        let slave = OpenOptions::new().read(true).write(true)
+            .custom_flags(libc::O_NOCTTY).open(&path)?;
+        std::mem::forget(slave);
+        eprintln!("[INFO]: serial port is {}", path);
+        Ok((Input::stream(master.try_clone()?), Box::new(master)))
+    }
+}
+
+#[cfg(not(unix))]
+fn open_pty() -> io::Result<(Input, Box<dyn Write + Send>)> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "pseudo terminals only work on unix"))
+}
@@ -0,0 +1,238 @@
+use std::fs;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::sync::{Arc, Mutex};
+
+/// Start of the sound registers, 4 channels of
+/// frequency low, frequency high, volume, control
+pub static SOUND_LOC: usize = 0x4100;
+/// Amount of channels, 0 and 1 are square, 2 is triangle and 3 is noise
+static SOUND_CHANNELS: usize = 4;
+/// Samples per second of the output
+static SAMPLE_RATE: u32 = 44100;
+/// Samples per buffer given to raylib, about 6ms so register writes are heard right away
+static BUFFER_SIZE: usize = 256;
+/// Clock speed the CPU is assumed to run at when writing a WAV file
+pub static CPU_HZ: u64 = 1_000_000;
+/// How many cycles to wait between checking if raylib wants more samples
+static POLL_CYCLES: u64 = 250;
+/// Where the sizes go in the WAV header, filled in once all samples are written
+static WAV_RIFF_SIZE: u64 = 4;
+static WAV_DATA_SIZE: u64 = 40;
+/// Most samples a WAV file can describe, the RIFF size is 36 + 2 bytes per sample in a u32
+static WAV_MAX_SAMPLES: u32 = (u32::MAX - 36) / 2;
+
+#[repr(u8)]
+/// Bits of a channels control register
+enum SoundControl {
+    // channel is playing
+    Enable  = 0b00000001,
+    // 2 bit duty cycle for square channels: 12.5%, 25%, 50%, 75%
+    Duty    = 0b00000110,
+}
+
+#[derive(Clone)]
+/// Where the samples go
+enum SoundOutput {
+    // nothing played yet
+    Off,
+    // theres no audio device, dont try again
+    Silent,
+    // played through raylib in real time
+    Raylib(raylib::ffi::AudioStream),
+    // written to a WAV file as they come, timed by CPU cycles
+    Wav {
+        file: Arc<Mutex<BufWriter<fs::File>>>,
+        // samples in the file so far
+        written: u32,
+        // cycles times SAMPLE_RATE, a sample is due every CPU_HZ
+        clock: u64
+    }
+}
+
+#[derive(Clone)]
+/// Simple 4 channel sound chip
+pub struct Sound {
+    /// the registers as written by the CPU
+    regs: [u8; 16],
+    /// position in the current wave of each channel, 0 to 1
+    phase: [f32; 4],
+    /// noise generator
+    lfsr: u16,
+    output: SoundOutput,
+    /// cycles since raylib was last checked
+    idle: u64,
+}
+
+impl Sound {
+    pub fn init() -> Sound {
+        Sound {
+            regs: [0; 16],
+            phase: [0.0; 4],
+            lfsr: 1,
+            output: SoundOutput::Off,
+            idle: 0,
+        }
+    }
+
+    /// Write the samples to a WAV file instead of playing them
+    pub fn headless(&mut self, path: &str) -> io::Result<()> {
+        let mut file = BufWriter::new(fs::File::create(path)?);
+        // the sizes get fixed up at the end
+        file.write_all(&wav_header(0))?;
+        self.output = SoundOutput::Wav {file: Arc::new(Mutex::new(file)), written: 0, clock: 0};
+        Ok(())
+    }
+
+    /// Start playing through raylib, only once something uses the sound chip
+    fn start(&mut self) {
+        if !matches!(self.output, SoundOutput::Off) {
+            return
+        }
+        unsafe {
+            // make raylib shut up
+            raylib::ffi::SetTraceLogLevel(7);
+            raylib::ffi::InitAudioDevice();
+            if !raylib::ffi::IsAudioDeviceReady() {
+                // no audio device, just stay silent
+                self.output = SoundOutput::Silent;
+                return
+            }
+            raylib::ffi::SetAudioStreamBufferSizeDefault(BUFFER_SIZE as i32);
+            let stream = raylib::ffi::LoadAudioStream(SAMPLE_RATE, 16, 1);
+            raylib::ffi::PlayAudioStream(stream);
+            self.output = SoundOutput::Raylib(stream);
+        }
+    }
+
+    /// Read a sound register
+    pub fn read(&self, addr: u16) -> u8 {
+        self.regs[addr as usize - SOUND_LOC]
+    }
+
+    /// Write a sound register
+    pub fn write(&mut self, addr: u16, byte: u8) {
+        self.regs[addr as usize - SOUND_LOC] = byte;
+        self.start();
+    }
+
+    /// Mix the next sample of all channels
+    fn next_sample(&mut self) -> i16 {
+        let mut mix = 0.0;
+        for ch in 0..SOUND_CHANNELS {
+            let regs = &self.regs[ch * 4..ch * 4 + 4];
+            let freq = (regs[1] as u16) << 8 | regs[0] as u16;
+            let volume = regs[2] as f32 / 255.0;
+            let control = regs[3];
+            if control & SoundControl::Enable as u8 == 0 || freq == 0 {
+                continue
+            }
+            let step = freq as f32 / SAMPLE_RATE as f32;
+            let phase = self.phase[ch] + step;
+            let wrapped = phase >= 1.0;
+            self.phase[ch] = phase.fract();
+            let phase = self.phase[ch];
+            let wave = match ch {
+                // square
+                0 | 1 => {
+                    let duty = [0.125, 0.25, 0.5, 0.75][((control & SoundControl::Duty as u8) >> 1) as usize];
+                    if phase < duty { 1.0 } else { -1.0 }
+                }
+                // triangle
+                2 => 1.0 - 4.0 * (phase - 0.5).abs(),
+                // noise, the frequency is how often the LFSR shifts
+                _ => {
+                    if wrapped {
+                        let bit = (self.lfsr ^ (self.lfsr >> 1)) & 1;
+                        self.lfsr = (self.lfsr >> 1) | (bit << 14);
+                    }
+                    if self.lfsr & 1 != 0 { 1.0 } else { -1.0 }
+                }
+            };
+            mix += wave * volume;
+        }
+        // leave some headroom so all channels at full volume dont clip
+        (mix / SOUND_CHANNELS as f32 * i16::MAX as f32 * 0.8) as i16
+    }
+
+    /// Let the sound chip know cycles have passed
+    pub fn tick(&mut self, cycles: u64) {
+        match self.output {
+            SoundOutput::Off | SoundOutput::Silent => {}
+            SoundOutput::Raylib(stream) => {
+                // asking raylib every instruction is slow
+                self.idle += cycles;
+                if self.idle < POLL_CYCLES {
+                    return
+                }
+                self.idle = 0;
+                unsafe {
+                    if raylib::ffi::IsAudioStreamProcessed(stream) {
+                        let buffer: Vec<i16> = (0..BUFFER_SIZE).map(|_| self.next_sample()).collect();
+                        raylib::ffi::UpdateAudioStream(stream, buffer.as_ptr() as *const _,
+                            BUFFER_SIZE as i32);
+                    }
+                }
+            }
+            SoundOutput::Wav {..} => {
+                let mut due = 0;
+                if let SoundOutput::Wav {clock, written, ..} = &mut self.output {
+                    *clock += cycles * SAMPLE_RATE as u64;
+                    // the file is full once the header cant describe more, the rest gets dropped
+                    due = (*clock / CPU_HZ).min((WAV_MAX_SAMPLES - *written) as u64) as u32;
+                    *clock %= CPU_HZ;
+                }
+                let bytes: Vec<u8> = (0..due).flat_map(|_| self.next_sample().to_le_bytes()).collect();
+                if let SoundOutput::Wav {file, written, ..} = &mut self.output {
+                    // the buffer only hits the disk every few KB, errors show up in finish
+                    let _ = file.lock().unwrap().write_all(&bytes);
+                    *written += due;
+                }
+            }
+        }
+    }
+
+    /// Stop playing or write the WAV file
+    pub fn finish(&mut self) -> Result<(), String> {
+        match &self.output {
+            SoundOutput::Off | SoundOutput::Silent => {}
+            SoundOutput::Raylib(stream) => unsafe {
+                raylib::ffi::UnloadAudioStream(*stream);
+                raylib::ffi::CloseAudioDevice();
+            }
+            SoundOutput::Wav {file, written, ..} => {
+                // written never goes past WAV_MAX_SAMPLES, so none of this overflows
+                let data_size = *written * 2;
+                let mut file = file.lock().unwrap();
+                file.flush()
+                    .and_then(|_| file.seek(SeekFrom::Start(WAV_RIFF_SIZE)))
+                    .and_then(|_| file.write_all(&(36 + data_size).to_le_bytes()))
+                    .and_then(|_| file.seek(SeekFrom::Start(WAV_DATA_SIZE)))
+                    .and_then(|_| file.write_all(&data_size.to_le_bytes()))
+                    .and_then(|_| file.flush())
+                    .map_err(|e| format!("IO Error: {}", e))?;
+            }
+        }
+        self.output = SoundOutput::Off;
+        Ok(())
+    }
+}
+
+/// Header of a WAV file with data_size bytes of 16 bit mono samples after it
+fn wav_header(data_size: u32) -> Vec<u8> {
+    let mut bytes: Vec<u8> = vec![];
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&data_size.saturating_add(36).to_le_bytes());
+    bytes.extend_from_slice(b"WAVE");
+    // format chunk: PCM, 1 channel, sample rate, byte rate, block align, bits per sample
+    bytes.extend_from_slice(b"fmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    bytes.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    bytes.extend_from_slice(&2u16.to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_size.to_le_bytes());
+    bytes
+}
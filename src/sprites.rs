@@ -0,0 +1,165 @@
+use crate::Bus;
+use crate::gpu::{GPU_MODE_LOC, GPU_MODE_WIDE};
+
+/// Start of the sprite attribute table
+pub static SPRITE_ATTR_LOC: usize = 0x6200;
+/// Amount of hardware sprites
+pub static SPRITE_COUNT: usize = 32;
+/// Size of one entry in the attribute table:
+/// x, y, pattern, r, g, b, flags, high bits of x and y (wide mode)
+pub static SPRITE_ATTR_SIZE: usize = 8;
+/// Collision status register, cleared on read
+pub static SPRITE_STATUS_LOC: usize = 0x6300;
+/// First sprite of the last collision
+pub static SPRITE_COLL_A_LOC: usize = 0x6301;
+/// Second sprite of the last collision
+pub static SPRITE_COLL_B_LOC: usize = 0x6302;
+/// Start of pattern memory, 256 patterns of 8x8 pixels at 1bpp
+pub static SPRITE_PAT_LOC: usize = 0x7800;
+/// Width and height of a pattern in pixels
+static PATTERN_SIZE: i32 = 8;
+/// Set in the status register if two sprites overlapped
+static COLLISION_MASK: u8 = 0b10000000;
+
+#[repr(u8)]
+/// Masks for the flags byte of a sprite
+enum SpriteFlag {
+    // Sprite gets drawn and checked for collisions
+    Enable  = 0b00000001,
+    // Mirror horizontally
+    FlipX   = 0b00000010,
+    // Mirror vertically
+    FlipY   = 0b00000100,
+    // Every pattern pixel is drawn as 2x2
+    Double  = 0b00001000,
+}
+
+/// One entry of the sprite attribute table
+struct Sprite {
+    x: i32,
+    y: i32,
+    pattern: u8,
+    col: raylib::ffi::Color,
+    flags: u8,
+}
+
+impl Sprite {
+    fn has(&self, flag: SpriteFlag) -> bool {
+        self.flags & flag as u8 != 0
+    }
+
+    /// Size on screen in logical pixels
+    fn size(&self) -> i32 {
+        if self.has(SpriteFlag::Double) { PATTERN_SIZE * 2 } else { PATTERN_SIZE }
+    }
+}
+
+impl Bus {
+    /// Read entry i of the attribute table
+    fn sprite(&self, i: usize) -> Sprite {
+        let base = SPRITE_ATTR_LOC - 0x6000 + i * SPRITE_ATTR_SIZE;
+        let attr = &self.gpu[base..base + SPRITE_ATTR_SIZE];
+        // in wide mode the last byte has bits 8-11 of x in the low nibble and of y in the high one
+        let high = if self.gpu[GPU_MODE_LOC - 0x6000] & GPU_MODE_WIDE != 0 {attr[7]} else {0};
+        Sprite {
+            x: ((high & 0x0F) as i32) << 8 | attr[0] as i32,
+            y: ((high >> 4) as i32) << 8 | attr[1] as i32,
+            pattern: attr[2],
+            col: raylib::ffi::Color {r: attr[3], g: attr[4], b: attr[5], a: 0xFF},
+            flags: attr[6],
+        }
+    }
+
+    /**
+    Is the pixel at px, py (logical pixels, relative to the sprite) set
+
+    Handles flipping and double size
+    */
+    fn sprite_pixel(&self, s: &Sprite, px: i32, py: i32) -> bool {
+        if px < 0 || py < 0 || px >= s.size() || py >= s.size() {
+            return false
+        }
+        // go back to pattern coordinates
+        let shift = if s.has(SpriteFlag::Double) { 1 } else { 0 };
+        let mut px = px >> shift;
+        let mut py = py >> shift;
+        if s.has(SpriteFlag::FlipX) {
+            px = PATTERN_SIZE - 1 - px;
+        }
+        if s.has(SpriteFlag::FlipY) {
+            py = PATTERN_SIZE - 1 - py;
+        }
+        // each row is one byte, leftmost pixel is bit 7
+        let row = self.gpu[SPRITE_PAT_LOC - 0x6000 + s.pattern as usize * 8 + py as usize];
+        row & (0b10000000 >> px) != 0
+    }
+
+    /// Latch the first pair of overlapping sprites into the status registers
+    fn check_sprite_collisions(&mut self) {
+        for i in 0..SPRITE_COUNT {
+            let a = self.sprite(i);
+            if !a.has(SpriteFlag::Enable) {
+                continue
+            }
+            for j in i + 1..SPRITE_COUNT {
+                let b = self.sprite(j);
+                if !b.has(SpriteFlag::Enable) {
+                    continue
+                }
+                // only check where the bounding boxes overlap
+                let (x0, x1) = (a.x.max(b.x), (a.x + a.size()).min(b.x + b.size()));
+                let (y0, y1) = (a.y.max(b.y), (a.y + a.size()).min(b.y + b.size()));
+                for y in y0..y1 {
+                    for x in x0..x1 {
+                        if self.sprite_pixel(&a, x - a.x, y - a.y) &&
+                            self.sprite_pixel(&b, x - b.x, y - b.y) {
+                            self.gpu[SPRITE_STATUS_LOC - 0x6000] = COLLISION_MASK;
+                            self.gpu[SPRITE_COLL_A_LOC - 0x6000] = i as u8;
+                            self.gpu[SPRITE_COLL_B_LOC - 0x6000] = j as u8;
+                            return
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /**
+    Draw all enabled sprites on top of the current frame and update
+    the collision status
+
+    Sprite 0 has the highest priority, so it gets drawn last
+    */
+    pub fn draw_sprites(&mut self) {
+        let scale = self.gpu_scale as i32;
+        for i in (0..SPRITE_COUNT).rev() {
+            let s = self.sprite(i);
+            if !s.has(SpriteFlag::Enable) {
+                continue
+            }
+            for py in 0..s.size() {
+                for px in 0..s.size() {
+                    if self.sprite_pixel(&s, px, py) {
+                        unsafe {
+                            raylib::ffi::DrawRectangle((s.x + px) * scale,
+                                (s.y + py) * scale,
+                                scale,
+                                scale,
+                                s.col);
+                        }
+                    }
+                }
+            }
+        }
+        self.check_sprite_collisions();
+    }
+
+    /// Read the collision status, reading $6300 clears the collision flag
+    pub fn read_sprite_status(&mut self, addr: u16) -> u8 {
+        let byte = self.gpu[addr as usize - 0x6000];
+        if addr as usize == SPRITE_STATUS_LOC {
+            self.gpu[SPRITE_STATUS_LOC - 0x6000] = 0;
+        }
+        byte
+    }
+}
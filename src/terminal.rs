@@ -0,0 +1,197 @@
+use std::collections::VecDeque;
+use std::io::{self, IsTerminal, Write};
+use std::sync::{Arc, Mutex};
+
+/// Key that was read last, popped from the input buffer
+pub static INPUT_KEY_LOC: u16 = 0x4001;
+/// 1 if the input buffer has keys in it
+pub static INPUT_READY_LOC: u16 = 0x4002;
+/// Amount of keys in the input buffer
+pub static INPUT_COUNT_LOC: u16 = 0x4003;
+/// Input status, see InputStatus, cleared on read
+pub static INPUT_STATUS_LOC: u16 = 0x4004;
+/// How many keys fit into the input buffer
+pub static INPUT_DEPTH_LOC: u16 = 0x4005;
+/// Input control, see InputControl
+pub static INPUT_CONTROL_LOC: u16 = 0x4006;
+/// Default size of the input buffer
+pub static INPUT_DEPTH: u8 = 32;
+
+#[repr(u8)]
+/// Bits of the input status register
+enum InputStatus {
+    // a key got dropped because the buffer was full
+    Overflow    = 0b10000000,
+}
+
+#[repr(u8)]
+/// Bits of the input control register
+enum InputControl {
+    // throw away all keys that werent read yet
+    Flush       = 0b00000001,
+}
+
+#[derive(Clone)]
+/// Terminal output at $4000 and the key buffer behind $4001
+pub struct Terminal {
+    /// Write bytes exactly as they are, without handling control characters
+    raw: bool,
+    /// Where the output goes instead of stdout, eg a socket
+    out: Option<Arc<Mutex<Box<dyn Write + Send>>>>,
+    /// Keys the CPU hasnt read yet, oldest first
+    keys: VecDeque<u8>,
+    depth: u8,
+    /// What $4001 gives back when the buffer is empty
+    last: u8,
+    status: u8,
+    /// The ROM asked for a key while the buffer was empty
+    starved: bool,
+}
+
+impl Terminal {
+    /// Output is raw by default if stdout isnt a terminal, eg when piped into a file
+    pub fn init() -> Terminal {
+        Terminal {
+            raw: !io::stdout().is_terminal(),
+            out: None,
+            keys: VecDeque::with_capacity(INPUT_DEPTH as usize),
+            depth: INPUT_DEPTH,
+            last: 0,
+            status: 0,
+            starved: false,
+        }
+    }
+
+    /// Change how many keys the input buffer holds, at least 1
+    pub fn set_input_depth(&mut self, depth: u8) {
+        self.depth = depth.max(1);
+        self.keys.truncate(self.depth as usize);
+    }
+
+    /// Whether another key would overflow the buffer
+    pub fn input_full(&self) -> bool {
+        self.keys.len() >= self.depth as usize
+    }
+
+    /// Add a key to the buffer, or drop it and set the overflow flag if theres no space
+    pub fn push_key(&mut self, byte: u8) {
+        if self.input_full() {
+            self.status |= InputStatus::Overflow as u8;
+            return
+        }
+        self.keys.push_back(byte);
+        self.starved = false;
+    }
+
+    /// Add an escape sequence, all of it or nothing so the ROM never sees half of one
+    pub fn push_keys(&mut self, bytes: &[u8]) {
+        if self.keys.len() + bytes.len() > self.depth as usize {
+            self.status |= InputStatus::Overflow as u8;
+            return
+        }
+        self.keys.extend(bytes);
+        self.starved = false;
+    }
+
+    /// Take the oldest key out of the buffer, for devices other than $4001
+    pub fn pop_key(&mut self) -> Option<u8> {
+        self.keys.pop_front()
+    }
+
+    /// Remember that the ROM is waiting for a key if there arent any
+    pub fn wait_for_key(&mut self) {
+        if self.keys.is_empty() {
+            self.starved = true;
+        }
+    }
+
+    /// Whether the ROM is waiting for a key since the last one came in
+    pub fn starved(&self) -> bool {
+        self.starved
+    }
+
+    /// Read the input registers
+    pub fn read(&mut self, addr: u16) -> u8 {
+        if addr == INPUT_KEY_LOC || addr == INPUT_READY_LOC || addr == INPUT_COUNT_LOC {
+            self.wait_for_key();
+        }
+        match addr {
+            a if a == INPUT_KEY_LOC => {
+                // keep giving back the last key once its empty, like the old single register
+                if let Some(byte) = self.keys.pop_front() {
+                    self.last = byte;
+                }
+                self.last
+            }
+            a if a == INPUT_READY_LOC => !self.keys.is_empty() as u8,
+            a if a == INPUT_COUNT_LOC => self.keys.len() as u8,
+            a if a == INPUT_STATUS_LOC => {
+                let status = self.status;
+                self.status = 0;
+                status
+            }
+            a if a == INPUT_DEPTH_LOC => self.depth,
+            _ => 0
+        }
+    }
+
+    /// Write the input registers, only the control register does anything
+    pub fn write_input(&mut self, addr: u16, byte: u8) {
+        if addr == INPUT_CONTROL_LOC && byte & InputControl::Flush as u8 != 0 {
+            self.keys.clear();
+        }
+    }
+
+    pub fn is_raw(&self) -> bool {
+        self.raw
+    }
+
+    /// Force raw output even on a terminal
+    pub fn set_raw(&mut self, raw: bool) {
+        self.raw = raw;
+    }
+
+    /// Send the output somewhere else than stdout, its raw since thats no terminal
+    pub fn set_output(&mut self, out: Box<dyn Write + Send>) {
+        self.out = Some(Arc::new(Mutex::new(out)));
+        self.raw = true;
+    }
+
+    /**
+    Write a byte to stdout
+
+    Escape sequences get passed through so ANSI cursor movement and colours work
+    */
+    pub fn write(&mut self, byte: u8) {
+        let single = [byte];
+        let mut utf8 = [0; 4];
+        let bytes: &[u8] = if self.raw {
+            &single
+        } else {
+            match byte {
+                // backspace, move back and erase the character
+                8 => b"\x08 \x08",
+                // newline, we need the CR since we're in raw mode
+                10 => b"\n\r",
+                // form feed, clear the screen and go to the top left
+                12 => b"\x1b[2J\x1b[H",
+                // tab, bell, CR, escape and everything else
+                _ => (byte as char).encode_utf8(&mut utf8).as_bytes()
+            }
+        };
+        // if the other end went away theres nobody to tell anyway
+        match &self.out {
+            Some(out) => {
+                if let Ok(mut out) = out.lock() {
+                    let _ = out.write_all(bytes);
+                    let _ = out.flush();
+                }
+            }
+            None => {
+                let mut out = io::stdout();
+                let _ = out.write_all(bytes);
+                let _ = out.flush();
+            }
+        }
+    }
+}
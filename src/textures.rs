@@ -0,0 +1,166 @@
+use crate::Bus;
+use crate::gpu::GpuStatus;
+
+/// Amount of texture slots
+pub static TEXTURE_SLOTS: usize = 16;
+/// Biggest width or height of a texture
+static TEXTURE_MAX_SIZE: i32 = 1024;
+/// raylib PIXELFORMAT_UNCOMPRESSED_R8G8B8A8
+static PIXELFORMAT_RGBA: i32 = 7;
+
+#[repr(u8)]
+/// Bits of the flags argument of DefineTexture
+enum DefineFlag {
+    // pixels with value 0 are see through
+    Transparent = 0b00000001,
+}
+
+#[repr(u8)]
+/// Bits of the flags argument of DrawTexture and DrawTile
+enum DrawFlag {
+    FlipX   = 0b00000001,
+    FlipY   = 0b00000010,
+}
+
+impl Bus {
+    /**
+    Turn a bitmap in 6502 memory into a texture
+
+    Arguments: slot, bitmap pointer (word), w, h, bits per pixel, palette base, flags
+
+    Every row starts on a new byte, the leftmost pixel is in the highest bits.
+    Pixel values get added to the palette base to get the colour
+    */
+    pub fn define_texture(&mut self) -> Result<(), GpuStatus> {
+        let slot = self.next_arg();
+        let ptr = self.next_arg() as u16 | (self.next_arg() as u16) << 8;
+        let width = self.next_coord();
+        let height = self.next_coord();
+        let bpp = self.next_arg();
+        let palette_base = self.next_arg();
+        let flags = self.next_arg();
+        if slot as usize >= TEXTURE_SLOTS || !matches!(bpp, 1 | 2 | 4 | 8) ||
+            width == 0 || height == 0 || width > TEXTURE_MAX_SIZE || height > TEXTURE_MAX_SIZE {
+            return Err(GpuStatus::BadArgument)
+        }
+        let row_bytes = (width as usize * bpp as usize).div_ceil(8);
+        // the bitmap has to fit into the 64K the 6502 can see
+        if row_bytes * height as usize > 0x10000 {
+            return Err(GpuStatus::BadArgument)
+        }
+        let mask = ((1u16 << bpp) - 1) as u8;
+        let mut pixels: Vec<u8> = Vec::with_capacity(width as usize * height as usize * 4);
+        for y in 0..height as usize {
+            for x in 0..width as usize {
+                let bit = x * bpp as usize;
+                let addr = ptr.wrapping_add((y * row_bytes + bit / 8) as u16);
+                let shift = 8 - bpp as usize - bit % 8;
+                let value = (self.read(addr) >> shift) & mask;
+                let col = self.palette_colour(palette_base.wrapping_add(value));
+                let alpha = if value == 0 && flags & DefineFlag::Transparent as u8 != 0 {0x00} else {0xFF};
+                pixels.extend_from_slice(&[col.r, col.g, col.b, alpha]);
+            }
+        }
+        let image = raylib::ffi::Image {
+            data: pixels.as_mut_ptr() as *mut _,
+            width,
+            height,
+            mipmaps: 1,
+            format: PIXELFORMAT_RGBA
+        };
+        unsafe {
+            // raylib copies the pixels, so the vec can go away after this
+            let texture = raylib::ffi::LoadTextureFromImage(image);
+            if let Some(old) = self.textures[slot as usize].replace(texture) {
+                raylib::ffi::UnloadTexture(old);
+            }
+        }
+        Ok(())
+    }
+
+    /**
+    Draw part of a texture, x and y are logical pixels like every other command
+    and scale is on top of the window scale
+    */
+    fn draw_texture_part(&mut self, texture: raylib::ffi::Texture2D, source: raylib::ffi::Rectangle,
+        x: i32, y: i32, scale: u8, flags: u8) {
+        let scale = scale.max(1) as f32 * self.gpu_scale as f32;
+        let mut source = source;
+        let dest = raylib::ffi::Rectangle {
+            x: x as f32 * self.gpu_scale as f32,
+            y: y as f32 * self.gpu_scale as f32,
+            width: source.width * scale,
+            height: source.height * scale
+        };
+        // negative sizes flip it
+        if flags & DrawFlag::FlipX as u8 != 0 {
+            source.width = -source.width;
+        }
+        if flags & DrawFlag::FlipY as u8 != 0 {
+            source.height = -source.height;
+        }
+        unsafe {
+            raylib::ffi::DrawTexturePro(texture, source, dest, raylib::ffi::Vector2 {x: 0.0, y: 0.0},
+                0.0, raylib::ffi::Color {r: 0xFF, g: 0xFF, b: 0xFF, a: 0xFF});
+        }
+    }
+
+    /**
+    Draw a whole texture
+
+    Arguments: slot, x, y, scale, flags
+    */
+    pub fn draw_texture(&mut self) -> Result<(), GpuStatus> {
+        let slot = self.next_arg();
+        let x = self.next_coord();
+        let y = self.next_coord();
+        let scale = self.next_arg();
+        let flags = self.next_arg();
+        let Some(Some(texture)) = self.textures.get(slot as usize).copied() else {
+            return Err(GpuStatus::BadArgument)
+        };
+        let source = raylib::ffi::Rectangle {
+            x: 0.0,
+            y: 0.0,
+            width: texture.width as f32,
+            height: texture.height as f32
+        };
+        self.draw_texture_part(texture, source, x, y, scale, flags);
+        Ok(())
+    }
+
+    /**
+    Draw one tile of a texture, tiles are numbered left to right,
+    then top to bottom
+
+    Arguments: slot, tile, tile w, tile h, x, y, scale, flags
+    */
+    pub fn draw_tile(&mut self) -> Result<(), GpuStatus> {
+        let slot = self.next_arg();
+        let tile = self.next_arg();
+        let tile_w = self.next_coord();
+        let tile_h = self.next_coord();
+        let x = self.next_coord();
+        let y = self.next_coord();
+        let scale = self.next_arg();
+        let flags = self.next_arg();
+        let Some(Some(texture)) = self.textures.get(slot as usize).copied() else {
+            return Err(GpuStatus::BadArgument)
+        };
+        if tile_w == 0 || tile_h == 0 || tile_w > texture.width {
+            return Err(GpuStatus::BadArgument)
+        }
+        let per_row = texture.width / tile_w;
+        let source = raylib::ffi::Rectangle {
+            x: (tile as i32 % per_row * tile_w) as f32,
+            y: (tile as i32 / per_row * tile_h) as f32,
+            width: tile_w as f32,
+            height: tile_h as f32
+        };
+        if source.y + source.height > texture.height as f32 {
+            return Err(GpuStatus::BadArgument)
+        }
+        self.draw_texture_part(texture, source, x, y, scale, flags);
+        Ok(())
+    }
+}
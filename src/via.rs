@@ -0,0 +1,347 @@
+/// Start of the 16 VIA registers
+pub static VIA_LOC: usize = 0x4020;
+
+// register numbers
+static ORB: usize = 0x0;
+static ORA: usize = 0x1;
+static DDRB: usize = 0x2;
+static DDRA: usize = 0x3;
+static T1CL: usize = 0x4;
+static T1CH: usize = 0x5;
+static T1LL: usize = 0x6;
+static T1LH: usize = 0x7;
+static T2CL: usize = 0x8;
+static T2CH: usize = 0x9;
+static SR: usize = 0xA;
+static ACR: usize = 0xB;
+static PCR: usize = 0xC;
+static IFR: usize = 0xD;
+static IER: usize = 0xE;
+/// Port A without clearing the handshake flags
+static ORA_NH: usize = 0xF;
+
+#[repr(u8)]
+/// Bits of the interrupt flag and enable registers
+enum ViaIrq {
+    // CA2 edge
+    Ca2     = 0b00000001,
+    // CA1 edge
+    Ca1     = 0b00000010,
+    // 8 bits shifted
+    Shift   = 0b00000100,
+    // CB2 edge
+    Cb2     = 0b00001000,
+    // CB1 edge
+    Cb1     = 0b00010000,
+    // timer 2 ran out
+    Timer2  = 0b00100000,
+    // timer 1 ran out
+    Timer1  = 0b01000000,
+    // any enabled flag is set (IFR), or set/clear (IER writes)
+    Any     = 0b10000000,
+}
+
+#[repr(u8)]
+/// Bits of the auxiliary control register
+enum ViaAcr {
+    // shift register mode, bits 2-4
+    ShiftMode   = 0b00011100,
+    // timer 2 counts pulses on PB6 instead of cycles
+    T2Pulses    = 0b00100000,
+    // timer 1 reloads from the latch and keeps going
+    T1FreeRun   = 0b01000000,
+    // timer 1 drives PB7
+    T1Pb7       = 0b10000000,
+}
+
+#[derive(Clone)]
+/// 6522 VIA with two timers, a shift register and two 8 bit ports
+pub struct Via {
+    /// Only there if turned on with --via
+    pub enabled: bool,
+    ora: u8,
+    orb: u8,
+    ddra: u8,
+    ddrb: u8,
+    /// What the outside world drives the input pins to, pulled high if nothing is connected
+    pub pins_a: u8,
+    pub pins_b: u8,
+    t1: u16,
+    t1_latch: u16,
+    /// Timer 1 sets its flag on the next time out, one shot mode only does it once
+    t1_armed: bool,
+    /// Level of PB7 when timer 1 drives it
+    pb7: bool,
+    t2: u16,
+    /// Timer 2 only has a low latch
+    t2_latch: u8,
+    t2_armed: bool,
+    sr: u8,
+    /// Bits left to shift
+    sr_count: u8,
+    /// Cycles towards the next shift
+    sr_cycles: u64,
+    acr: u8,
+    pcr: u8,
+    ifr: u8,
+    ier: u8,
+}
+
+impl Via {
+    pub fn init() -> Via {
+        Via {
+            enabled: false,
+            ora: 0,
+            orb: 0,
+            ddra: 0,
+            ddrb: 0,
+            pins_a: 0xFF,
+            pins_b: 0xFF,
+            t1: 0xFFFF,
+            t1_latch: 0xFFFF,
+            t1_armed: false,
+            pb7: true,
+            t2: 0xFFFF,
+            t2_latch: 0xFF,
+            t2_armed: false,
+            sr: 0,
+            sr_count: 0,
+            sr_cycles: 0,
+            acr: 0,
+            pcr: 0,
+            ifr: 0,
+            ier: 0,
+        }
+    }
+
+    /// Levels of the port A pins, outputs from ORA and inputs from outside
+    pub fn port_a(&self) -> u8 {
+        self.ora & self.ddra | self.pins_a & !self.ddra
+    }
+
+    /// Levels of the port B pins, PB7 can come from timer 1
+    pub fn port_b(&self) -> u8 {
+        let mut port = self.orb & self.ddrb | self.pins_b & !self.ddrb;
+        if self.acr & ViaAcr::T1Pb7 as u8 != 0 {
+            port = port & 0x7F | (self.pb7 as u8) << 7;
+        }
+        port
+    }
+
+    fn shift_mode(&self) -> u8 {
+        (self.acr & ViaAcr::ShiftMode as u8) >> 2
+    }
+
+    /// Reading or writing the shift register starts 8 new shifts
+    fn start_shift(&mut self) {
+        self.ifr &= !(ViaIrq::Shift as u8);
+        self.sr_count = 8;
+        self.sr_cycles = 0;
+    }
+
+    /// Whether the VIA pulls the IRQ line
+    pub fn irq(&self) -> bool {
+        self.enabled && self.ifr & self.ier & 0x7F != 0
+    }
+
+    /// Read a register, some of them clear flags
+    pub fn read(&mut self, addr: u16) -> u8 {
+        let reg = addr as usize - VIA_LOC;
+        match reg {
+            r if r == ORB => {
+                self.ifr &= !(ViaIrq::Cb1 as u8 | ViaIrq::Cb2 as u8);
+                self.port_b()
+            }
+            r if r == ORA => {
+                self.ifr &= !(ViaIrq::Ca1 as u8 | ViaIrq::Ca2 as u8);
+                self.port_a()
+            }
+            r if r == ORA_NH => self.port_a(),
+            r if r == DDRB => self.ddrb,
+            r if r == DDRA => self.ddra,
+            r if r == T1CL => {
+                self.ifr &= !(ViaIrq::Timer1 as u8);
+                self.t1 as u8
+            }
+            r if r == T1CH => (self.t1 >> 8) as u8,
+            r if r == T1LL => self.t1_latch as u8,
+            r if r == T1LH => (self.t1_latch >> 8) as u8,
+            r if r == T2CL => {
+                self.ifr &= !(ViaIrq::Timer2 as u8);
+                self.t2 as u8
+            }
+            r if r == T2CH => (self.t2 >> 8) as u8,
+            r if r == SR => {
+                self.start_shift();
+                self.sr
+            }
+            r if r == ACR => self.acr,
+            r if r == PCR => self.pcr,
+            r if r == IFR => {
+                let any = if self.ifr & self.ier & 0x7F != 0 {ViaIrq::Any as u8} else {0};
+                self.ifr | any
+            }
+            // bit 7 always reads as 1
+            r if r == IER => self.ier | ViaIrq::Any as u8,
+            _ => 0
+        }
+    }
+
+    /// Write a register
+    pub fn write(&mut self, addr: u16, byte: u8) {
+        let reg = addr as usize - VIA_LOC;
+        match reg {
+            r if r == ORB => {
+                self.ifr &= !(ViaIrq::Cb1 as u8 | ViaIrq::Cb2 as u8);
+                self.orb = byte;
+            }
+            r if r == ORA => {
+                self.ifr &= !(ViaIrq::Ca1 as u8 | ViaIrq::Ca2 as u8);
+                self.ora = byte;
+            }
+            r if r == ORA_NH => self.ora = byte,
+            r if r == DDRB => self.ddrb = byte,
+            r if r == DDRA => self.ddra = byte,
+            // the low byte only goes into the latch
+            r if r == T1CL || r == T1LL => self.t1_latch = self.t1_latch & 0xFF00 | byte as u16,
+            r if r == T1CH => {
+                // load the counter and start
+                self.t1_latch = self.t1_latch & 0x00FF | (byte as u16) << 8;
+                self.t1 = self.t1_latch;
+                self.t1_armed = true;
+                self.ifr &= !(ViaIrq::Timer1 as u8);
+                // PB7 goes low until the timer runs out
+                self.pb7 = false;
+            }
+            r if r == T1LH => {
+                self.t1_latch = self.t1_latch & 0x00FF | (byte as u16) << 8;
+                self.ifr &= !(ViaIrq::Timer1 as u8);
+            }
+            r if r == T2CL => self.t2_latch = byte,
+            r if r == T2CH => {
+                self.t2 = (byte as u16) << 8 | self.t2_latch as u16;
+                self.t2_armed = true;
+                self.ifr &= !(ViaIrq::Timer2 as u8);
+            }
+            r if r == SR => {
+                self.sr = byte;
+                self.start_shift();
+            }
+            r if r == ACR => self.acr = byte,
+            r if r == PCR => self.pcr = byte,
+            // writing a 1 clears that flag
+            r if r == IFR => self.ifr &= !(byte & 0x7F),
+            // bit 7 says if the other bits get set or cleared
+            r if r == IER => {
+                if byte & ViaIrq::Any as u8 != 0 {
+                    self.ier |= byte & 0x7F;
+                } else {
+                    self.ier &= !byte;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Count the timers down and run the shift register
+    pub fn tick(&mut self, cycles: u64) {
+        if !self.enabled {
+            return
+        }
+        self.tick_t1(cycles);
+        self.tick_t2(cycles);
+        self.tick_shift(cycles);
+    }
+
+    fn tick_t1(&mut self, cycles: u64) {
+        let count = self.t1 as u64;
+        // it times out one cycle after reaching 0
+        if cycles <= count {
+            self.t1 -= cycles as u16;
+            return
+        }
+        let mut left = cycles - count - 1;
+        let mut timeouts = 1;
+        if self.acr & ViaAcr::T1FreeRun as u8 != 0 {
+            // reloading takes another cycle, so the period is latch + 2
+            let period = self.t1_latch as u64 + 2;
+            timeouts += left / period;
+            left %= period;
+            self.t1 = self.t1_latch.wrapping_sub(left as u16);
+            if self.t1_armed {
+                self.ifr |= ViaIrq::Timer1 as u8;
+            }
+            // PB7 makes a square wave
+            if timeouts % 2 == 1 {
+                self.pb7 = !self.pb7;
+            }
+        } else {
+            // one shot, keeps counting down but only flags once
+            self.t1 = 0xFFFFu16.wrapping_sub(left as u16);
+            if self.t1_armed {
+                self.ifr |= ViaIrq::Timer1 as u8;
+                self.pb7 = true;
+                self.t1_armed = false;
+            }
+        }
+    }
+
+    fn tick_t2(&mut self, cycles: u64) {
+        // theres nothing connected to PB6 to count
+        if self.acr & ViaAcr::T2Pulses as u8 != 0 {
+            return
+        }
+        let count = self.t2 as u64;
+        if cycles <= count {
+            self.t2 -= cycles as u16;
+            return
+        }
+        self.t2 = 0xFFFFu16.wrapping_sub((cycles - count - 1) as u16);
+        if self.t2_armed {
+            self.ifr |= ViaIrq::Timer2 as u8;
+            self.t2_armed = false;
+        }
+    }
+
+    /**
+    Shift bits in or out, the shift clock runs at half the CPU clock or
+    timer 2s low latch. Shifting in reads CB2, which nothing drives so its high.
+    Modes clocked by CB1 from outside never get anywhere
+    */
+    fn tick_shift(&mut self, cycles: u64) {
+        let mode = self.shift_mode();
+        let rate = match mode {
+            // under timer 2
+            0b001 | 0b100 | 0b101 => 2 * (self.t2_latch as u64 + 2),
+            // under the CPU clock
+            0b010 | 0b110 => 2,
+            // disabled or external clock
+            _ => return
+        };
+        let free_running = mode == 0b100;
+        if self.sr_count == 0 && !free_running {
+            return
+        }
+        self.sr_cycles += cycles;
+        let shifts = self.sr_cycles / rate;
+        self.sr_cycles %= rate;
+        if free_running {
+            // just keeps going round without ever setting the flag
+            self.sr = self.sr.rotate_left((shifts % 8) as u32);
+            return
+        }
+        let shifts = shifts.min(self.sr_count as u64) as u8;
+        for _ in 0..shifts {
+            if mode & 0b100 != 0 {
+                // shifting out, the bit going out comes back in at the bottom
+                self.sr = self.sr.rotate_left(1);
+            } else {
+                self.sr = self.sr << 1 | 1;
+            }
+        }
+        self.sr_count -= shifts;
+        if self.sr_count == 0 {
+            self.ifr |= ViaIrq::Shift as u8;
+        }
+    }
+}
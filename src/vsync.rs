@@ -0,0 +1,146 @@
+use std::time::{Duration, Instant};
+
+use crate::Bus;
+
+/// Frames since the window opened, 32 bit little endian
+pub static FRAME_COUNT_LOC: usize = 0x6108;
+/// VBlank status, see VSyncStatus, cleared on read
+pub static VSYNC_STATUS_LOC: usize = 0x610C;
+/// VBlank control, see VSyncControl
+pub static VSYNC_CONTROL_LOC: usize = 0x610D;
+/// How many cycles to wait between checking the time in free running mode
+static VSYNC_POLL_CYCLES: u64 = 1000;
+
+#[repr(u8)]
+/// Bits of the status register
+enum VSyncStatus {
+    // a vblank happened since the last read
+    VBlank  = 0b10000000,
+}
+
+#[repr(u8)]
+/// Bits of the control register
+enum VSyncControl {
+    // raise an IRQ on vblank until the status gets read
+    Irq         = 0b00000001,
+    // EndDrawing doesnt wait, vblanks come from a timer at the ROMs framerate
+    FreeRunning = 0b00000010,
+}
+
+#[derive(Clone)]
+/// Frame counter and vblank flag of the GPU
+pub struct VSync {
+    frames: u32,
+    /// What the frame counter reads as, taken when the low byte is read
+    latched: u32,
+    status: u8,
+    control: u8,
+    /// framerate from the ROM header
+    pub fps: u8,
+    /// when the last vblank happened in free running mode
+    last: Instant,
+    /// cycles since the time was last checked
+    idle: u64,
+}
+
+impl VSync {
+    pub fn init() -> VSync {
+        VSync {
+            frames: 0,
+            latched: 0,
+            status: 0,
+            control: 0,
+            fps: 0,
+            last: Instant::now(),
+            idle: 0,
+        }
+    }
+
+    fn free_running(&self) -> bool {
+        self.control & VSyncControl::FreeRunning as u8 != 0
+    }
+
+    /// Count a frame and set the flag
+    fn vblank(&mut self) {
+        self.frames = self.frames.wrapping_add(1);
+        self.status |= VSyncStatus::VBlank as u8;
+    }
+
+    /// A frame was presented with EndDrawing
+    pub fn end_drawing(&mut self) {
+        // in free running mode the timer does this
+        if !self.free_running() {
+            self.vblank();
+        }
+    }
+
+    /// Check the timer in free running mode
+    pub fn tick(&mut self, cycles: u64) {
+        if !self.free_running() || self.fps == 0 {
+            return
+        }
+        // asking for the time every instruction is slow
+        self.idle += cycles;
+        if self.idle < VSYNC_POLL_CYCLES {
+            return
+        }
+        self.idle = 0;
+        let period = Duration::from_secs(1) / self.fps as u32;
+        if self.last.elapsed() >= period {
+            self.last += period;
+            // dont try to catch up after a long stall
+            if self.last.elapsed() >= period {
+                self.last = Instant::now();
+            }
+            self.vblank();
+        }
+    }
+
+    /// Whether a vblank IRQ is pending
+    pub fn irq(&self) -> bool {
+        self.control & VSyncControl::Irq as u8 != 0 && self.status & VSyncStatus::VBlank as u8 != 0
+    }
+}
+
+impl Bus {
+    /// Read the frame counter and vblank registers
+    pub fn read_vsync(&mut self, addr: u16) -> u8 {
+        let vs = &mut self.vsync;
+        let addr = addr as usize;
+        if (FRAME_COUNT_LOC..FRAME_COUNT_LOC + 4).contains(&addr) {
+            // so a frame ending between the reads cant mix up the bytes
+            if addr == FRAME_COUNT_LOC {
+                vs.latched = vs.frames;
+            }
+            return vs.latched.to_le_bytes()[addr - FRAME_COUNT_LOC]
+        }
+        if addr == VSYNC_STATUS_LOC {
+            // reading acknowledges the vblank
+            let status = vs.status;
+            vs.status = 0;
+            return status
+        }
+        if addr == VSYNC_CONTROL_LOC {
+            return vs.control
+        }
+        0
+    }
+
+    /// Write the vblank control register
+    pub fn write_vsync(&mut self, addr: u16, byte: u8) {
+        if addr as usize != VSYNC_CONTROL_LOC {
+            return
+        }
+        let was_free = self.vsync.free_running();
+        self.vsync.control = byte;
+        let free = self.vsync.free_running();
+        if was_free != free && self.gpu_enable {
+            unsafe {
+                // raylib waits in EndDrawing to hit the target framerate,
+                // 0 means it doesnt wait at all
+                raylib::ffi::SetTargetFPS(if free {0} else {self.vsync.fps as i32});
+            }
+            self.vsync.last = Instant::now();
+        }
+    }
+}
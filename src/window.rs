@@ -0,0 +1,188 @@
+use crate::Bus;
+
+/// Header version, 0 for ROMs that only have the fields up to the framerate
+pub static W_VERSION_LOC: usize = 0xfff9;
+/// Window flags, see WindowFlag (header version 1)
+pub static W_FLAGS_LOC: usize = 0xffec;
+/// Palette index of the colour around the picture (header version 1)
+pub static W_BG_LOC: usize = 0xffed;
+/// Pixel aspect ratio, width then height, 0 counts as 1 (header version 1)
+pub static W_ASPECT_LOC: usize = 0xffee;
+
+// raylib ConfigFlags
+static FLAG_FULLSCREEN_MODE: u32 = 0x00000002;
+static FLAG_WINDOW_RESIZABLE: u32 = 0x00000004;
+// raylib TextureFilter
+static TEXTURE_FILTER_POINT: i32 = 0;
+static TEXTURE_FILTER_BILINEAR: i32 = 1;
+
+#[repr(u8)]
+/// Bits of the window flags in the header
+enum WindowFlag {
+    // the window can be resized
+    Resizable   = 0b00000001,
+    // start in fullscreen
+    Fullscreen  = 0b00000010,
+    // scale by any amount with filtering instead of whole multiples
+    Smooth      = 0b00000100,
+    // stretch to the whole window in smooth mode instead of keeping the aspect ratio
+    Stretch     = 0b00001000,
+}
+
+#[derive(Clone)]
+/// Window options from the ROM header
+pub struct Window {
+    flags: u8,
+    /// Palette index around the picture
+    bg: u8,
+    /// How wide and how high one pixel is compared to the other
+    aspect: (u8, u8),
+    /// What the ROM draws into if the window size can change
+    target: Option<raylib::ffi::RenderTexture2D>,
+}
+
+impl Window {
+    pub fn init() -> Window {
+        Window {flags: 0, bg: 0, aspect: (1, 1), target: None}
+    }
+
+    fn has(&self, flag: WindowFlag) -> bool {
+        self.flags & flag as u8 != 0
+    }
+
+    /// raylib config flags to set before the window gets created
+    pub fn config_flags(&self) -> u32 {
+        let mut flags = 0;
+        if self.has(WindowFlag::Resizable) {
+            flags |= FLAG_WINDOW_RESIZABLE;
+        }
+        if self.has(WindowFlag::Fullscreen) {
+            flags |= FLAG_FULLSCREEN_MODE;
+        }
+        flags
+    }
+
+    fn square_pixels(&self) -> bool {
+        self.aspect.0 == self.aspect.1
+    }
+
+    /// Size of the picture once the pixels have their aspect ratio, only ever stretched, never squashed
+    fn display_size(&self, width: f32, height: f32) -> (f32, f32) {
+        let (w, h) = (self.aspect.0 as f32, self.aspect.1 as f32);
+        if w >= h {
+            (width * w / h, height)
+        } else {
+            (width, height * h / w)
+        }
+    }
+
+    /// How big the window starts out for a picture of width x height
+    pub fn window_size(&self, width: i32, height: i32) -> (i32, i32) {
+        let (w, h) = self.display_size(width as f32, height as f32);
+        (w.round() as i32, h.round() as i32)
+    }
+
+    /**
+    Create the render target if the window can end up a different
+    size than width x height or the pixels arent square, has to be
+    called after InitWindow
+    */
+    pub fn open(&mut self, width: i32, height: i32) {
+        if !self.has(WindowFlag::Resizable) && !self.has(WindowFlag::Fullscreen) && self.square_pixels() {
+            // draw straight to the window like before
+            return
+        }
+        unsafe {
+            let target = raylib::ffi::LoadRenderTexture(width, height);
+            let filter = if self.has(WindowFlag::Smooth) {TEXTURE_FILTER_BILINEAR} else {TEXTURE_FILTER_POINT};
+            raylib::ffi::SetTextureFilter(target.texture, filter);
+            self.target = Some(target);
+        }
+    }
+
+    /// Where the picture goes in the window
+    fn dest(&self, target: &raylib::ffi::RenderTexture2D) -> raylib::ffi::Rectangle {
+        let (win_w, win_h) = unsafe {
+            (raylib::ffi::GetScreenWidth() as f32, raylib::ffi::GetScreenHeight() as f32)
+        };
+        let (w, h) = self.display_size(target.texture.width as f32, target.texture.height as f32);
+        if self.has(WindowFlag::Smooth) && self.has(WindowFlag::Stretch) {
+            return raylib::ffi::Rectangle {x: 0.0, y: 0.0, width: win_w, height: win_h}
+        }
+        let mut k = (win_w / w).min(win_h / h);
+        if !self.has(WindowFlag::Smooth) {
+            // whole multiples only, so every pixel is the same size
+            k = k.floor().max(1.0);
+        }
+        // center it
+        raylib::ffi::Rectangle {
+            x: ((win_w - w * k) / 2.0).floor(),
+            y: ((win_h - h * k) / 2.0).floor(),
+            width: w * k,
+            height: h * k
+        }
+    }
+
+    /// Turn window coordinates into coordinates in the picture
+    pub fn map_mouse(&self, x: i32, y: i32) -> (i32, i32) {
+        let Some(target) = &self.target else {
+            return (x, y)
+        };
+        let dest = self.dest(target);
+        let x = (x as f32 - dest.x) / dest.width * target.texture.width as f32;
+        let y = (y as f32 - dest.y) / dest.height * target.texture.height as f32;
+        (x as i32, y as i32)
+    }
+}
+
+impl Bus {
+    /// Read the window options from the ROM header
+    pub fn read_window_header(&mut self) {
+        // older ROMs have 0 here since the header gets filled
+        if self.read(W_VERSION_LOC as u16) >= 1 {
+            self.window.flags = self.read(W_FLAGS_LOC as u16);
+            self.window.bg = self.read(W_BG_LOC as u16);
+            let w = self.read(W_ASPECT_LOC as u16).max(1);
+            let h = self.read(W_ASPECT_LOC as u16 + 1).max(1);
+            self.window.aspect = (w, h);
+        }
+    }
+
+    /// BeginDrawing, or draw into the render target
+    pub fn begin_frame(&mut self) {
+        unsafe {
+            match self.window.target {
+                Some(target) => raylib::ffi::BeginTextureMode(target),
+                None => raylib::ffi::BeginDrawing(),
+            }
+        }
+    }
+
+    /// EndDrawing, scaling the render target to the window first if there is one
+    pub fn end_frame(&mut self) {
+        let Some(target) = self.window.target else {
+            unsafe {
+                raylib::ffi::EndDrawing();
+            }
+            return
+        };
+        let bg = self.palette_colour(self.window.bg);
+        let dest = self.window.dest(&target);
+        unsafe {
+            raylib::ffi::EndTextureMode();
+            raylib::ffi::BeginDrawing();
+            raylib::ffi::ClearBackground(bg);
+            // render textures are upside down
+            let source = raylib::ffi::Rectangle {
+                x: 0.0,
+                y: 0.0,
+                width: target.texture.width as f32,
+                height: -target.texture.height as f32
+            };
+            raylib::ffi::DrawTexturePro(target.texture, source, dest,
+                raylib::ffi::Vector2 {x: 0.0, y: 0.0}, 0.0,
+                raylib::ffi::Color {r: 0xFF, g: 0xFF, b: 0xFF, a: 0xFF});
+            raylib::ffi::EndDrawing();
+        }
+    }
+}